@@ -0,0 +1,515 @@
+//! `gwasm` - command-line tool for building, submitting and tracking gWasm
+//! tasks on Golem
+//!
+//! The tool works on task workspaces: `gwasm build` creates a workspace with
+//! the task's dirs, manifest and sidecar, and the remaining subcommands load
+//! the task from the workspace. The ID of a submitted task is stored in the
+//! workspace too, so that the task can be tracked, aborted, and its results
+//! collected by later invocations.
+use actix::System;
+use futures::stream::StreamExt;
+use gwasm_api::golem;
+use gwasm_api::prelude::*;
+use gwasm_api::spec::TaskSpec;
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use structopt::StructOpt;
+
+/// File in the workspace storing the ID of the submitted task
+const TASK_ID_FILE: &str = "golem-task-id";
+
+// parsed once, so the size of the variants doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "gwasm",
+    about = "Builds, submits and tracks gWasm tasks on Golem"
+)]
+enum Command {
+    /// Builds a task in a workspace from a dir of inputs, a manifest or a
+    /// task spec
+    Build(BuildArgs),
+    /// Submits a task built in a workspace to Golem
+    Submit {
+        #[structopt(flatten)]
+        task: TaskArgs,
+        #[structopt(flatten)]
+        node: GolemArgs,
+        /// Waits for the task to be computed, and collects its results
+        #[structopt(long)]
+        wait: bool,
+        /// Dir to copy the output files of the subtasks to, with --wait
+        #[structopt(long, short, parse(from_os_str), requires = "wait")]
+        output: Option<PathBuf>,
+    },
+    /// Checks the status of a submitted task
+    Status {
+        #[structopt(flatten)]
+        task: TaskArgs,
+        #[structopt(flatten)]
+        node: GolemArgs,
+    },
+    /// Follows the progress of a submitted task until it finishes
+    Watch {
+        #[structopt(flatten)]
+        task: TaskArgs,
+        #[structopt(flatten)]
+        node: GolemArgs,
+    },
+    /// Aborts a submitted task
+    Abort {
+        #[structopt(flatten)]
+        task: TaskArgs,
+        #[structopt(flatten)]
+        node: GolemArgs,
+    },
+    /// Collects the results of a computed task
+    Collect {
+        /// Workspace of the task
+        #[structopt(parse(from_os_str))]
+        workspace: PathBuf,
+        /// Dir to copy the output files of the subtasks to
+        #[structopt(long, short, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct BuildArgs {
+    /// Workspace to build the task in, created if it doesn't exist
+    #[structopt(parse(from_os_str))]
+    workspace: PathBuf,
+    /// Path prefix of the Emscripten build output, e.g. `build/app` for
+    /// `build/app.js` and `build/app.wasm`
+    #[structopt(long, parse(from_os_str), required_unless_one = &["js", "spec"])]
+    binary: Option<PathBuf>,
+    /// JavaScript file generated by Emscripten
+    #[structopt(long, parse(from_os_str), conflicts_with = "binary", requires = "wasm")]
+    js: Option<PathBuf>,
+    /// Wasm binary generated by Emscripten
+    #[structopt(long, parse(from_os_str), conflicts_with = "binary", requires = "js")]
+    wasm: Option<PathBuf>,
+    /// Dir whose every file is the input of one subtask, in order of the
+    /// file names
+    #[structopt(long, parse(from_os_str), required_unless_one = &["manifest", "spec"])]
+    inputs: Option<PathBuf>,
+    /// File listing one subtask per line: the path to its input file,
+    /// followed by its args separated by whitespace; relative paths are
+    /// relative to the manifest, and lines starting with `#` are ignored
+    #[structopt(long, parse(from_os_str), conflicts_with = "inputs")]
+    manifest: Option<PathBuf>,
+    /// Task spec file in JSON, TOML or YAML format, describing the binary
+    /// and the subtasks; the remaining options override the spec's values
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["binary", "js", "wasm", "inputs", "manifest"]
+    )]
+    spec: Option<PathBuf>,
+    /// Name of the task
+    #[structopt(long)]
+    name: Option<String>,
+    /// Bid for the task
+    #[structopt(long)]
+    bid: Option<f64>,
+    /// Budget of the task
+    #[structopt(long)]
+    budget: Option<f64>,
+    /// Hard cap on spending, at which the task is aborted
+    #[structopt(long)]
+    budget_cap: Option<f64>,
+    /// Timeout of the task, e.g. `01:30:00` or `90m`
+    #[structopt(long)]
+    timeout: Option<Timeout>,
+    /// Timeout of each subtask, e.g. `00:10:00` or `10m`
+    #[structopt(long)]
+    subtask_timeout: Option<Timeout>,
+    /// Dir where the final output of the task is expected
+    #[structopt(long, parse(from_os_str))]
+    output_path: Option<PathBuf>,
+    /// File shared by all subtasks, placed in the task's input dir
+    #[structopt(long, parse(from_os_str), number_of_values = 1)]
+    shared: Vec<PathBuf>,
+    /// Number of threads writing the subtasks' inputs
+    #[structopt(long, default_value = "1")]
+    parallelism: usize,
+    /// Hard-links the input files into the workspace instead of copying them
+    #[structopt(long)]
+    hard_link: bool,
+    /// Metadata attached to the task, as `KEY=VALUE`
+    #[structopt(long, parse(try_from_str = parse_key_value), number_of_values = 1)]
+    metadata: Vec<(String, String)>,
+    /// Replaces the task's dirs if they already exist in the workspace, and
+    /// forgets the ID of the task submitted from it
+    #[structopt(long)]
+    overwrite: bool,
+    /// Writes the task's JSON manifest, exactly as it would be sent to
+    /// Golem, to the given file, and reports what would be submitted
+    #[structopt(long, parse(from_os_str))]
+    dry_run: Option<PathBuf>,
+    /// Args passed to every subtask listed with --inputs
+    #[structopt(last = true, conflicts_with_all = &["manifest", "spec"])]
+    args: Vec<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct TaskArgs {
+    /// Workspace of the task
+    #[structopt(parse(from_os_str))]
+    workspace: PathBuf,
+    /// ID of the task on Golem, instead of the one stored in the workspace
+    #[structopt(long)]
+    task_id: Option<String>,
+}
+
+impl TaskArgs {
+    fn task_id(&self) -> Result<String> {
+        if let Some(task_id) = &self.task_id {
+            return Ok(task_id.clone());
+        }
+        let path = self.workspace.join(TASK_ID_FILE);
+        let task_id = fs::read_to_string(&path).map_err(|e| Error::FileError(e, path))?;
+        Ok(task_id.trim().to_owned())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct GolemArgs {
+    /// Golem's data dir
+    #[structopt(long, parse(from_os_str))]
+    datadir: PathBuf,
+    /// Address of Golem's RPC endpoint
+    #[structopt(long, default_value = "127.0.0.1")]
+    address: String,
+    /// Port of Golem's RPC endpoint
+    #[structopt(long, default_value = "61000")]
+    port: u16,
+    /// Connects to Golem on the mainnet instead of the testnet
+    #[structopt(long)]
+    mainnet: bool,
+}
+
+impl GolemArgs {
+    fn net(&self) -> Net {
+        if self.mainnet {
+            Net::MainNet
+        } else {
+            Net::TestNet
+        }
+    }
+}
+
+struct ProgressPrinter;
+
+impl ProgressUpdate for ProgressPrinter {
+    fn update(&self, progress: f64) {
+        println!("progress: {:.1}%", progress * 100.0);
+    }
+
+    fn update_spent(&self, spent: f64, budget: Option<f64>) {
+        match budget {
+            Some(budget) => println!("spent: {} of {}", spent, budget),
+            None => println!("spent: {}", spent),
+        }
+    }
+}
+
+fn main() {
+    if let Err(err) = run(Command::from_args()) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Build(args) => build(args),
+        Command::Submit {
+            task,
+            node,
+            wait,
+            output,
+        } => submit(task, node, wait, output),
+        Command::Status { task, node } => status(task, node),
+        Command::Watch { task, node } => watch(task, node),
+        Command::Abort { task, node } => abort(task, node),
+        Command::Collect { workspace, output } => {
+            collect(Task::load(&workspace)?, output.as_deref())
+        }
+    }
+}
+
+fn block_on<F: Future + 'static>(future: F) -> F::Output {
+    let mut system = System::new("gwasm");
+    system.block_on(future)
+}
+
+fn build(args: BuildArgs) -> Result<()> {
+    fs::create_dir_all(&args.workspace).map_err(|e| Error::FileError(e, args.workspace.clone()))?;
+
+    let (mut builder, num_subtasks) = match &args.spec {
+        Some(spec) => {
+            let spec = TaskSpec::from_file(spec)?;
+            let num_subtasks = spec.subtasks.len();
+            (spec.into_builder(&args.workspace)?, num_subtasks)
+        }
+        None => {
+            let binary = match (&args.binary, &args.js, &args.wasm) {
+                (Some(prefix), _, _) => GWasmBinary::from_prefix(prefix)?,
+                (None, Some(js), Some(wasm)) => GWasmBinary::from_files(js, wasm)?,
+                _ => unreachable!("binary args are checked by the parser"),
+            };
+            let subtasks = match (&args.inputs, &args.manifest) {
+                (Some(dir), _) => read_inputs_dir(dir, &args.args)?,
+                (None, Some(manifest)) => read_manifest(manifest)?,
+                _ => unreachable!("input args are checked by the parser"),
+            };
+            let num_subtasks = subtasks.len();
+            let mut builder = TaskBuilder::try_new(&args.workspace, binary)?;
+            for (path, subtask_args) in subtasks {
+                builder = builder
+                    .push_subtask_file(path, file_mode(&args))
+                    .subtask_args(subtask_args);
+            }
+            (builder, num_subtasks)
+        }
+    };
+
+    builder = builder.parallelism(args.parallelism);
+    if let Some(name) = &args.name {
+        builder = builder.name(name);
+    }
+    if let Some(bid) = args.bid {
+        builder = builder.bid(bid);
+    }
+    if let Some(budget) = args.budget {
+        builder = builder.budget(budget);
+    }
+    if let Some(budget_cap) = args.budget_cap {
+        builder = builder.budget_cap(budget_cap);
+    }
+    if let Some(timeout) = args.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(subtask_timeout) = args.subtask_timeout {
+        builder = builder.subtask_timeout(subtask_timeout);
+    }
+    if let Some(output_path) = &args.output_path {
+        builder = builder.output_path(output_path);
+    }
+    for (key, value) in &args.metadata {
+        builder = builder.metadata(key, value);
+    }
+    if args.overwrite {
+        builder = builder.workspace_policy(WorkspacePolicy::Overwrite);
+    }
+    for path in &args.shared {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| Error::InvalidSharedInputName(path.display().to_string()))?;
+        builder =
+            builder.push_shared_input(name, SubtaskInput::File(path.clone(), file_mode(&args)));
+    }
+
+    let task = builder.build()?;
+    // a task submitted before the workspace was rebuilt is not the one built
+    let task_id_path = args.workspace.join(TASK_ID_FILE);
+    match fs::remove_file(&task_id_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            return Err(Error::FileError(e, task_id_path));
+        }
+        _ => {}
+    }
+    println!(
+        "built task {} with {} subtasks in {}",
+        task.name(),
+        num_subtasks,
+        args.workspace.display()
+    );
+    match &args.dry_run {
+        Some(manifest_path) => println!("dry run: {}", golem::dry_run(&task, manifest_path)?),
+        None => println!("estimated cost: {}", task.estimated_cost()),
+    }
+    Ok(())
+}
+
+fn parse_key_value(value: &str) -> std::result::Result<(String, String), String> {
+    let mut parts = value.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(key), Some(value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err(format!("expected KEY=VALUE, found \"{}\"", value)),
+    }
+}
+
+fn file_mode(args: &BuildArgs) -> FileMode {
+    if args.hard_link {
+        FileMode::HardLink
+    } else {
+        FileMode::Copy
+    }
+}
+
+/// Lists the files in `dir` in order of their names, each with `args`
+fn read_inputs_dir(dir: &Path, args: &[String]) -> Result<Vec<(PathBuf, Vec<String>)>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| Error::FileError(e, dir.to_owned()))? {
+        let path = entry
+            .map_err(|e| Error::FileError(e, dir.to_owned()))?
+            .path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .map(|path| (path, args.to_vec()))
+        .collect())
+}
+
+/// Reads the input files and args of the subtasks listed in `manifest`
+fn read_manifest(manifest: &Path) -> Result<Vec<(PathBuf, Vec<String>)>> {
+    let file = File::open(manifest).map_err(|e| Error::FileError(e, manifest.to_owned()))?;
+    let base = manifest.parent().unwrap_or_else(|| Path::new(""));
+    let mut subtasks = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| Error::FileError(e, manifest.to_owned()))?;
+        let mut words = line.split_whitespace();
+        let path = match words.next() {
+            Some(word) if !word.starts_with('#') => base.join(word),
+            _ => continue,
+        };
+        subtasks.push((path, words.map(String::from).collect()));
+    }
+    Ok(subtasks)
+}
+
+fn submit(args: TaskArgs, node: GolemArgs, wait: bool, output: Option<PathBuf>) -> Result<()> {
+    let task = Task::load(&args.workspace)?;
+    let path = args.workspace.join(TASK_ID_FILE);
+    let computed_task = block_on(async move {
+        let (endpoint, task_id) = golem::create_task(
+            &node.datadir,
+            &node.address,
+            node.port,
+            node.net(),
+            task.clone(),
+        )
+        .await?;
+        // stored before waiting, so that the task can be tracked and
+        // aborted from elsewhere in the meantime
+        fs::write(&path, &task_id).map_err(|e| Error::FileError(e, path))?;
+        println!("submitted task {}", task_id);
+        if !wait {
+            return Ok(None);
+        }
+        golem::track_task(endpoint, task_id, task, ProgressPrinter, None)
+            .await
+            .map(Some)
+    })?;
+
+    match computed_task {
+        Some(computed_task) => report(computed_task, output.as_deref()),
+        None => Ok(()),
+    }
+}
+
+fn status(args: TaskArgs, node: GolemArgs) -> Result<()> {
+    let task_id = args.task_id()?;
+    let polled_task_id = task_id.clone();
+    let task_status = block_on(async move {
+        let endpoint = golem::connect(&node.datadir, &node.address, node.port, node.net()).await?;
+        let updates = golem::poll_task_progress(endpoint, polled_task_id.clone(), None);
+        futures::pin_mut!(updates);
+        updates.next().await.ok_or(Error::EmptyTaskInfo {
+            task_id: polled_task_id,
+        })?
+    })?;
+    print_status(&task_id, &task_status);
+    Ok(())
+}
+
+fn watch(args: TaskArgs, node: GolemArgs) -> Result<()> {
+    let task_id = args.task_id()?;
+    let polled_task_id = task_id.clone();
+    block_on(async move {
+        let endpoint = golem::connect(&node.datadir, &node.address, node.port, node.net()).await?;
+        let updates = golem::poll_task_progress(endpoint, polled_task_id.clone(), None);
+        futures::pin_mut!(updates);
+        while let Some(task_status) = updates.next().await {
+            print_status(&polled_task_id, &task_status?);
+        }
+        Ok::<_, Error>(())
+    })?;
+    println!("task {} finished", task_id);
+    Ok(())
+}
+
+fn print_status(task_id: &str, task_status: &golem::TaskStatus) {
+    let status = task_status
+        .status()
+        .map_or_else(|| "unknown".to_owned(), |status| format!("{:?}", status));
+    print!(
+        "task {}: {}, progress: {:.1}%",
+        task_id,
+        status,
+        task_status.progress() * 100.0
+    );
+    match task_status.spent() {
+        Some(spent) => println!(", spent: {}", spent),
+        None => println!(),
+    }
+}
+
+fn abort(args: TaskArgs, node: GolemArgs) -> Result<()> {
+    let task_id = args.task_id()?;
+    let aborted_task_id = task_id.clone();
+    block_on(async move {
+        let endpoint = golem::connect(&node.datadir, &node.address, node.port, node.net()).await?;
+        golem::abort_task(&endpoint, aborted_task_id).await
+    })?;
+    println!("aborted task {}", task_id);
+    Ok(())
+}
+
+fn collect(task: Task, output: Option<&Path>) -> Result<()> {
+    report(ComputedTask::try_from(task)?, output)
+}
+
+/// Prints the results of `computed_task`, copying the output files of the
+/// subtasks into `output`, if given
+fn report(computed_task: ComputedTask, output: Option<&Path>) -> Result<()> {
+    for mut subtask in computed_task.subtasks {
+        for (path, reader) in &mut subtask.data {
+            println!(
+                "{}: {} {}",
+                subtask.name,
+                path.display(),
+                subtask.digests[path]
+            );
+            if let Some(output) = output {
+                let relative_path = path.strip_prefix(Component::RootDir).unwrap_or(path);
+                let target = output.join(&subtask.name).join(relative_path);
+                copy_output(reader, &target)?;
+            }
+        }
+    }
+    for failed in &computed_task.failed_subtasks {
+        eprintln!("{}: failed: {}", failed.name, failed.error);
+    }
+    Ok(())
+}
+
+fn copy_output(reader: &mut BufReader<File>, target: &Path) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::FileError(e, parent.to_owned()))?;
+    }
+    let mut file = File::create(target).map_err(|e| Error::FileError(e, target.to_owned()))?;
+    io::copy(reader, &mut file).map_err(|e| Error::FileError(e, target.to_owned()))?;
+    Ok(())
+}
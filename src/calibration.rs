@@ -0,0 +1,454 @@
+//! Estimation of the task's timeouts from a local benchmark run
+//!
+//! Picking the [subtask timeout] is guesswork: too low, and the subtasks fail,
+//! too high, and the budget gets locked up. A [`Calibrator`] runs a few
+//! subtasks of a built [`Task`] locally, measures their wall time, and suggests
+//! the task's timeouts based on the slowest of them.
+//!
+//! # Example:
+//! ```rust,no_run
+//! use gwasm_api::calibration::{Calibrator, SpWasm};
+//! use gwasm_api::task::{GWasmBinary, TaskBuilder};
+//!
+//! let binary = GWasmBinary::from_prefix("app").unwrap();
+//! let mut task = TaskBuilder::try_new("workspace", binary)
+//!     .unwrap()
+//!     .push_subtask_data(vec![0u8; 100])
+//!     .build()
+//!     .unwrap();
+//! let runner = SpWasm::new().program("/opt/sp-wasm/wasm-sandbox");
+//! let calibration = Calibrator::with_runner(runner)
+//!     .samples(1)
+//!     .safety_factor(4.0)
+//!     .calibrate(&task)
+//!     .unwrap();
+//! println!("suggested subtask timeout: {}", calibration.subtask_timeout);
+//! calibration.apply(&mut task).unwrap();
+//! ```
+//!
+//! [subtask timeout]: ../task/struct.TaskBuilder.html#method.subtask_timeout
+//! [`Calibrator`]: struct.Calibrator.html
+//! [`Task`]: ../task/struct.Task.html
+use super::{
+    error::{Error, FileContext},
+    input::{FileMode, SubtaskInput},
+    task::{Subtask, Task},
+    timeout::Timeout,
+    Result,
+};
+use std::{
+    convert::TryFrom,
+    fs,
+    path::{Component, Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
+};
+
+/// Runs a subtask of a [`Task`] on the local machine
+///
+/// [`Task`]: ../task/struct.Task.html
+pub trait LocalRunner {
+    /// Runs `subtask` of `task` to completion
+    ///
+    /// `input_dir` contains the task's JavaScript and Wasm files, shared
+    /// inputs, and the subtask's own inputs, while the subtask's outputs are
+    /// expected in `output_dir`.
+    fn run(
+        &self,
+        task: &Task,
+        subtask: &Subtask,
+        input_dir: &Path,
+        output_dir: &Path,
+    ) -> Result<()>;
+}
+
+/// [`LocalRunner`] using the `wasm-sandbox` binary of [sp-wasm], the sandbox
+/// gWasm providers run the subtasks in
+///
+/// [`LocalRunner`]: trait.LocalRunner.html
+/// [sp-wasm]: https://github.com/golemfactory/sp-wasm
+#[derive(Debug, Clone)]
+pub struct SpWasm {
+    program: PathBuf,
+}
+
+impl SpWasm {
+    /// Creates a new `SpWasm` instance, which looks for `wasm-sandbox` in `PATH`
+    pub fn new() -> Self {
+        Self {
+            program: PathBuf::from("wasm-sandbox"),
+        }
+    }
+
+    /// Sets the path to the `wasm-sandbox` binary
+    pub fn program<P: Into<PathBuf>>(mut self, program: P) -> Self {
+        self.program = program.into();
+        self
+    }
+}
+
+impl Default for SpWasm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalRunner for SpWasm {
+    fn run(
+        &self,
+        task: &Task,
+        subtask: &Subtask,
+        input_dir: &Path,
+        output_dir: &Path,
+    ) -> Result<()> {
+        let mut command = Command::new(&self.program);
+        command
+            .arg("-I")
+            .arg(input_dir)
+            .arg("-O")
+            .arg(output_dir)
+            .arg("-j")
+            .arg(input_dir.join(task.options().js_name()))
+            .arg("-w")
+            .arg(input_dir.join(task.options().wasm_name()));
+        for output in subtask.output_file_paths() {
+            command.arg("-o").arg(output);
+        }
+        command.arg("--").args(subtask.exec_args());
+
+        let output = command.output().file_context(&self.program)?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::LocalRunFailed(format!(
+                "{} ({}): {}",
+                self.program.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            )))
+        }
+    }
+}
+
+/// Runs sample subtasks of a [`Task`] locally to estimate its timeouts
+///
+/// By default, the first subtask is run once with [`SpWasm`], and the timeouts
+/// are set to 3 times its wall time, assuming that all subtasks are computed
+/// concurrently.
+///
+/// [`Task`]: ../task/struct.Task.html
+/// [`SpWasm`]: struct.SpWasm.html
+#[derive(Debug, Clone)]
+pub struct Calibrator<R = SpWasm> {
+    runner: R,
+    samples: usize,
+    safety_factor: f64,
+    concurrency: Option<usize>,
+}
+
+impl Calibrator<SpWasm> {
+    /// Creates a new `Calibrator` instance using [`SpWasm`]
+    ///
+    /// [`SpWasm`]: struct.SpWasm.html
+    pub fn new() -> Self {
+        Self::with_runner(SpWasm::new())
+    }
+}
+
+impl Default for Calibrator<SpWasm> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: LocalRunner> Calibrator<R> {
+    /// Creates a new `Calibrator` instance using `runner`
+    pub fn with_runner(runner: R) -> Self {
+        Self {
+            runner,
+            samples: 1,
+            safety_factor: 3.0,
+            concurrency: None,
+        }
+    }
+
+    /// Sets the number of subtasks to run, starting from the first one
+    pub fn samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Sets the factor by which the wall time of the slowest sample is
+    /// multiplied to get the subtask timeout
+    ///
+    /// Providers are often slower than the local machine, and need time to
+    /// transfer the inputs and outputs, so the factor should comfortably
+    /// exceed 1. Unless it's a finite, positive number, [`calibrate`] fails
+    /// with [`Error::InvalidTimeout`].
+    ///
+    /// [`calibrate`]: struct.Calibrator.html#method.calibrate
+    /// [`Error::InvalidTimeout`]: ../error/enum.Error.html#variant.InvalidTimeout
+    pub fn safety_factor(mut self, safety_factor: f64) -> Self {
+        self.safety_factor = safety_factor;
+        self
+    }
+
+    /// Sets the number of subtasks expected to be computed concurrently,
+    /// which determines the task timeout
+    ///
+    /// By default, all subtasks are assumed to be computed concurrently, so
+    /// the task timeout equals the subtask timeout.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency.max(1));
+        self
+    }
+
+    /// Runs the sample subtasks of `task`, and suggests its timeouts
+    ///
+    /// The subtasks are run in temporary dirs, leaving the task's workspace
+    /// intact. Fails with [`Error::LocalRunFailed`] if a subtask fails, or
+    /// doesn't create all of its outputs, and with [`Error::InvalidTimeout`] if
+    /// the safety factor is invalid, or the suggested timeouts overflow.
+    ///
+    /// [`Error::LocalRunFailed`]: ../error/enum.Error.html#variant.LocalRunFailed
+    /// [`Error::InvalidTimeout`]: ../error/enum.Error.html#variant.InvalidTimeout
+    pub fn calibrate(&self, task: &Task) -> Result<Calibration> {
+        if !(self.safety_factor.is_finite() && self.safety_factor > 0.0) {
+            return Err(Error::InvalidTimeout {
+                value: self.safety_factor.to_string(),
+                reason: "safety factor must be finite and positive",
+            });
+        }
+        let subtasks: Vec<_> = task
+            .options()
+            .subtasks()
+            .filter(|(name, _)| !task.is_replica(name))
+            .collect();
+        if subtasks.is_empty() {
+            return Err(Error::LocalRunFailed("task has no subtasks".into()));
+        }
+
+        let mut durations = Vec::new();
+        for (name, subtask) in subtasks.iter().take(self.samples) {
+            let duration = self
+                .run_subtask(task, name, subtask)
+                .map_err(|err| match err {
+                    Error::LocalRunFailed(reason) => {
+                        Error::LocalRunFailed(format!("subtask {}: {}", name, reason))
+                    }
+                    err => err,
+                })?;
+            durations.push((String::from(*name), duration));
+        }
+
+        let slowest = durations
+            .iter()
+            .map(|(_, duration)| *duration)
+            .max()
+            .unwrap_or_default();
+        let subtask_timeout =
+            Duration::try_from_secs_f64(slowest.as_secs_f64() * self.safety_factor)
+                .map_err(|_| timeout_overflow(format!("{:?} * {}", slowest, self.safety_factor)))?;
+        let subtask_timeout = timeout_at_least_1s(subtask_timeout);
+        let concurrency = self.concurrency.unwrap_or(subtasks.len());
+        let rounds = subtasks.len() / concurrency + usize::from(subtasks.len() % concurrency != 0);
+        let timeout = u32::try_from(rounds)
+            .ok()
+            .and_then(|rounds| subtask_timeout.as_duration().checked_mul(rounds))
+            .ok_or_else(|| timeout_overflow(format!("{} * {}", subtask_timeout, rounds)))?;
+
+        Ok(Calibration {
+            durations,
+            subtask_timeout,
+            timeout: timeout_at_least_1s(timeout),
+        })
+    }
+
+    fn run_subtask(&self, task: &Task, name: &str, subtask: &Subtask) -> Result<Duration> {
+        let sandbox = tempfile::Builder::new()
+            .prefix("gwasm-calibration")
+            .tempdir()
+            .file_context(std::env::temp_dir())?;
+        let input_dir = sandbox.path().join("in");
+        let output_dir = sandbox.path().join("out");
+        fs::create_dir(&input_dir).file_context(&input_dir)?;
+        fs::create_dir(&output_dir).file_context(&output_dir)?;
+
+        // the task's files at the root of its input dir are available to all
+        // subtasks, next to the subtask's own inputs
+        let task_input_dir = task.options().input_dir_path();
+        link_files(task_input_dir, &input_dir)?;
+        link_files(&task_input_dir.join(name), &input_dir)?;
+
+        let start = Instant::now();
+        self.runner.run(task, subtask, &input_dir, &output_dir)?;
+        let duration = start.elapsed();
+
+        for output in subtask.output_file_paths() {
+            let relative_path = output.strip_prefix(Component::RootDir).unwrap_or(output);
+            if !output_dir.join(relative_path).is_file() {
+                return Err(Error::LocalRunFailed(format!(
+                    "missing output {}",
+                    output.display()
+                )));
+            }
+        }
+
+        Ok(duration)
+    }
+}
+
+/// Links all files directly within `from` into `to`
+fn link_files(from: &Path, to: &Path) -> Result<()> {
+    for entry in fs::read_dir(from).file_context(from)? {
+        let entry = entry.file_context(from)?;
+        if entry.file_type().file_context(entry.path())?.is_file() {
+            SubtaskInput::File(entry.path(), FileMode::HardLink)
+                .write_to(&to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+fn timeout_overflow(value: String) -> Error {
+    Error::InvalidTimeout {
+        value,
+        reason: "exceeds the maximum duration",
+    }
+}
+
+fn timeout_at_least_1s(duration: Duration) -> Timeout {
+    Timeout::try_from(duration.max(Duration::from_secs(1)))
+        .expect("timeout of at least 1s is valid")
+}
+
+/// Timeouts suggested by a [`Calibrator`]
+///
+/// [`Calibrator`]: struct.Calibrator.html
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    /// Wall times of the sample subtasks, by subtask name
+    pub durations: Vec<(String, Duration)>,
+    /// Suggested subtask timeout
+    pub subtask_timeout: Timeout,
+    /// Suggested task timeout
+    pub timeout: Timeout,
+}
+
+impl Calibration {
+    /// Sets the timeouts of `task` to the suggested values
+    ///
+    /// The task's JSON manifest in its workspace is updated as well, so that
+    /// the task [loaded] from the workspace, e.g., by `gwasm submit`, is
+    /// submitted with the suggested timeouts. Note that the [estimated cost]
+    /// of the task changes along with the subtask timeout.
+    ///
+    /// [loaded]: ../task/struct.Task.html#method.load
+    /// [estimated cost]: ../task/struct.Task.html#method.estimated_cost
+    pub fn apply(&self, task: &mut Task) -> Result<()> {
+        task.set_timeouts(self.timeout, self.subtask_timeout)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::task::{GWasmBinary, TaskBuilder};
+    use std::thread;
+
+    // copies the input to the output, taking 100ms for every byte of the input
+    struct SlowCopy;
+
+    impl LocalRunner for SlowCopy {
+        fn run(
+            &self,
+            task: &Task,
+            subtask: &Subtask,
+            input_dir: &Path,
+            output_dir: &Path,
+        ) -> Result<()> {
+            assert!(input_dir.join(task.options().js_name()).is_file());
+            let args: Vec<_> = subtask.exec_args().collect();
+            let input = fs::read(input_dir.join(args[0])).unwrap();
+            thread::sleep(Duration::from_millis(100) * input.len() as u32);
+            fs::write(output_dir.join(args[1]), input).unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn calibrate() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let mut task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .push_subtask_data(vec![0u8; 1])
+            .push_subtask_data(vec![0u8; 20])
+            .push_subtask_data(vec![0u8; 1])
+            .build()
+            .unwrap();
+
+        let calibration = Calibrator::with_runner(SlowCopy)
+            .samples(2)
+            .safety_factor(2.0)
+            .concurrency(2)
+            .calibrate(&task)
+            .unwrap();
+        assert_eq!(calibration.durations.len(), 2);
+        assert!(calibration.durations[1].1 >= Duration::from_secs(2));
+        assert!(calibration.subtask_timeout.as_secs() >= 4);
+        assert_eq!(
+            calibration.timeout.as_secs(),
+            calibration.subtask_timeout.as_secs() * 2
+        );
+
+        calibration.apply(&mut task).unwrap();
+        assert_eq!(task.subtask_timeout(), &calibration.subtask_timeout);
+        let loaded = Task::load(&workspace).unwrap();
+        assert_eq!(loaded.subtask_timeout(), &calibration.subtask_timeout);
+        assert_eq!(loaded.timeout(), &calibration.timeout);
+        // the local runs leave no trace in the workspace
+        let output_dir = task.options().output_dir_path().join("subtask_0");
+        assert_eq!(fs::read_dir(output_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn overflowing_timeout() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .push_subtask_data(vec![0u8; 1])
+            .build()
+            .unwrap();
+
+        let result = Calibrator::with_runner(SlowCopy)
+            .safety_factor(f64::MAX)
+            .calibrate(&task);
+        match result {
+            Err(Error::InvalidTimeout { .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_safety_factor() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .push_subtask_data(vec![0u8; 1])
+            .build()
+            .unwrap();
+
+        for factor in &[0.0, -1.0, f64::INFINITY, f64::NAN] {
+            let result = Calibrator::with_runner(SlowCopy)
+                .safety_factor(*factor)
+                .calibrate(&task);
+            match result {
+                Err(Error::InvalidTimeout { .. }) => {}
+                other => panic!("unexpected result for {}: {:?}", factor, other),
+            }
+        }
+    }
+}
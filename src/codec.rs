@@ -0,0 +1,129 @@
+//! Codecs for typed subtask inputs and outputs
+//!
+//! Instead of hand-serializing every subtask's input into `Vec<u8>`, and
+//! parsing every subtask's output from a [`BufReader`], the input can be
+//! pushed with [`TaskBuilder::push_subtask_value`] and the outputs decoded
+//! with [`ComputedTask::decode_outputs`], using one of the [`Codec`]s:
+//! * [`Json`], always available
+//! * [`Bincode`], available with the `bincode` feature
+//! * [`Cbor`], available with the `cbor` feature
+//!
+//! Note that the Wasm app has to use the same encoding to read its input,
+//! and to write its output.
+//!
+//! # Example:
+//! ```
+//! use gwasm_api::codec::Json;
+//! use gwasm_api::task::{GWasmBinary, TaskBuilder};
+//! use tempfile::tempdir;
+//!
+//! let binary = GWasmBinary::new(Vec::new(), Vec::new());
+//! let workspace = tempdir().unwrap();
+//! let task = TaskBuilder::try_new(&workspace, binary)
+//!     .unwrap()
+//!     .push_subtask_value::<Json, _>(&(0u32, 100u32))
+//!     .unwrap()
+//!     .push_subtask_value::<Json, _>(&(100u32, 200u32))
+//!     .unwrap()
+//!     .build();
+//! assert!(task.is_ok());
+//! ```
+//!
+//! [`BufReader`]: https://doc.rust-lang.org/std/io/struct.BufReader.html
+//! [`TaskBuilder::push_subtask_value`]: ../task/struct.TaskBuilder.html#method.push_subtask_value
+//! [`ComputedTask::decode_outputs`]: ../task/struct.ComputedTask.html#method.decode_outputs
+//! [`Codec`]: trait.Codec.html
+//! [`Json`]: struct.Json.html
+//! [`Bincode`]: struct.Bincode.html
+//! [`Cbor`]: struct.Cbor.html
+use super::{error::Error, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::Read;
+
+/// Trait specifying the required interface for a subtask data encoding
+pub trait Codec {
+    /// Serializes `value` into bytes
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>>;
+    /// Deserializes a value from `reader`
+    fn decode<T: DeserializeOwned, R: Read>(reader: R) -> Result<T>;
+}
+
+/// JSON encoding
+#[derive(Debug, Clone, Copy)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        serde_json::to_vec(value).map_err(|e| Error::CodecError(e.into()))
+    }
+
+    fn decode<T: DeserializeOwned, R: Read>(reader: R) -> Result<T> {
+        serde_json::from_reader(reader).map_err(|e| Error::CodecError(e.into()))
+    }
+}
+
+/// [bincode](https://docs.rs/bincode) encoding
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl Codec for Bincode {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        bincode::serialize(value).map_err(|e| Error::CodecError(e.into()))
+    }
+
+    fn decode<T: DeserializeOwned, R: Read>(reader: R) -> Result<T> {
+        bincode::deserialize_from(reader).map_err(|e| Error::CodecError(e.into()))
+    }
+}
+
+/// [CBOR](https://cbor.io) encoding
+#[cfg(feature = "cbor")]
+#[derive(Debug, Clone, Copy)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Codec for Cbor {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        serde_cbor::to_vec(value).map_err(|e| Error::CodecError(e.into()))
+    }
+
+    fn decode<T: DeserializeOwned, R: Read>(reader: R) -> Result<T> {
+        serde_cbor::from_reader(reader).map_err(|e| Error::CodecError(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn roundtrip<C: Codec>() {
+        let value = (42u32, String::from("gwasm"), vec![1.5f64, 2.5]);
+        let bytes = C::encode(&value).unwrap();
+        let decoded: (u32, String, Vec<f64>) = C::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        roundtrip::<Json>();
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_roundtrip() {
+        roundtrip::<Bincode>();
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_roundtrip() {
+        roundtrip::<Cbor>();
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(Json::decode::<u32, _>(&b"gwasm"[..]).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+//! Static analysis of gWasm binaries for sp-wasm sandbox compatibility
+//!
+//! Golem providers run gWasm binaries inside the [sp-wasm] sandbox which
+//! supports only a limited subset of syscalls and Emscripten imports. A binary
+//! importing e.g. networking or threading functions will fail on every provider,
+//! so such imports are best detected before the task is submitted.
+//!
+//! [`TaskBuilder`] can be configured to either reject, or only warn about
+//! incompatible binaries (see [`TaskBuilder::compat_policy`]).
+//!
+//! [sp-wasm]: https://github.com/golemfactory/sp-wasm
+//! [`TaskBuilder`]: ../task/struct.TaskBuilder.html
+//! [`TaskBuilder::compat_policy`]: ../task/struct.TaskBuilder.html#method.compat_policy
+use super::{
+    task::GWasmBinary,
+    wasm::{ExternalKind, Import, WasmModule},
+    Result,
+};
+use std::fmt;
+
+/// Specifies how incompatible binaries are treated when building a task
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum CompatPolicy {
+    /// The binary is not analyzed
+    #[default]
+    Ignore,
+    /// The incompatibilities are recorded in the [`Task`], and can be
+    /// inspected with [`Task::incompatibilities`]
+    ///
+    /// [`Task`]: ../task/struct.Task.html
+    /// [`Task::incompatibilities`]: ../task/struct.Task.html#method.incompatibilities
+    Warn,
+    /// The task is not built, and [`Error::IncompatibleBinary`] is returned
+    ///
+    /// [`Error::IncompatibleBinary`]: ../error/enum.Error.html#variant.IncompatibleBinary
+    Reject,
+}
+
+/// Category of functionality unsupported by the sp-wasm sandbox
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Category {
+    /// Sockets and name resolution
+    Networking,
+    /// Threads and synchronization primitives shared between threads
+    Threads,
+    /// Spawning and signalling processes
+    Processes,
+    /// Filesystem calls beyond reading and writing regular files
+    Filesystem,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let category = match self {
+            Self::Networking => "networking",
+            Self::Threads => "threads",
+            Self::Processes => "processes",
+            Self::Filesystem => "filesystem",
+        };
+        write!(f, "{}", category)
+    }
+}
+
+/// Import of a Wasm module unsupported by the sp-wasm sandbox
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incompatibility {
+    /// The unsupported import
+    pub import: Import,
+    /// Category of the unsupported functionality
+    pub category: Category,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{} ({})",
+            self.import.module, self.import.name, self.category
+        )
+    }
+}
+
+const NETWORKING: &[&str] = &[
+    "socket",
+    "socketpair",
+    "socketcall",
+    "bind",
+    "connect",
+    "listen",
+    "accept",
+    "accept4",
+    "getsockopt",
+    "setsockopt",
+    "getsockname",
+    "getpeername",
+    "sendto",
+    "sendmsg",
+    "recvfrom",
+    "recvmsg",
+    "shutdown",
+    "getaddrinfo",
+    "getnameinfo",
+    "gethostbyname",
+    "gethostbyaddr",
+];
+
+const THREADS: &[&str] = &[
+    "pthread_create",
+    "pthread_join",
+    "pthread_detach",
+    "pthread_exit",
+    "pthread_kill",
+];
+
+const PROCESSES: &[&str] = &[
+    "fork", "vfork", "execve", "wait4", "waitpid", "kill", "system", "popen",
+];
+
+const FILESYSTEM: &[&str] = &[
+    "link",
+    "linkat",
+    "symlink",
+    "symlinkat",
+    "readlink",
+    "readlinkat",
+    "chmod",
+    "fchmod",
+    "fchmodat",
+    "chown",
+    "chown32",
+    "fchown32",
+    "lchown32",
+    "fchownat",
+    "mknod",
+    "mknodat",
+    "mount",
+    "umount2",
+];
+
+// Emscripten's fastcomp backend imports syscalls by their i386 numbers,
+// e.g., `___syscall102` for `socketcall`
+const SYSCALLS: &[(u32, Category)] = &[
+    (2, Category::Processes),
+    (9, Category::Filesystem),
+    (11, Category::Processes),
+    (14, Category::Filesystem),
+    (15, Category::Filesystem),
+    (21, Category::Filesystem),
+    (37, Category::Processes),
+    (52, Category::Filesystem),
+    (83, Category::Filesystem),
+    (85, Category::Filesystem),
+    (94, Category::Filesystem),
+    (102, Category::Networking),
+    (114, Category::Processes),
+    (190, Category::Processes),
+    (198, Category::Filesystem),
+    (207, Category::Filesystem),
+    (212, Category::Filesystem),
+];
+
+fn categorize(name: &str) -> Option<Category> {
+    // WASI calls
+    if name.starts_with("sock_") {
+        return Some(Category::Networking);
+    }
+    if name == "proc_raise" {
+        return Some(Category::Processes);
+    }
+    if name == "path_link" || name == "path_symlink" || name == "path_readlink" {
+        return Some(Category::Filesystem);
+    }
+
+    // Emscripten imports are prefixed with underscores, and syscalls
+    // additionally with `syscall_` or `sys_`
+    let name = name.trim_start_matches('_');
+    if name.starts_with("emscripten_websocket") {
+        return Some(Category::Networking);
+    }
+    if name.contains("futex") || name.starts_with("emscripten_thread") {
+        return Some(Category::Threads);
+    }
+    if let Some(number) = name.strip_prefix("syscall") {
+        if let Ok(number) = number.parse::<u32>() {
+            return SYSCALLS
+                .iter()
+                .find(|(syscall, _)| *syscall == number)
+                .map(|(_, category)| *category);
+        }
+    }
+    let name = name
+        .trim_start_matches("syscall_")
+        .trim_start_matches("sys_");
+
+    if NETWORKING.contains(&name) {
+        Some(Category::Networking)
+    } else if THREADS.iter().any(|thread_fn| name.starts_with(thread_fn)) {
+        Some(Category::Threads)
+    } else if PROCESSES.contains(&name) {
+        Some(Category::Processes)
+    } else if FILESYSTEM.contains(&name) {
+        Some(Category::Filesystem)
+    } else {
+        None
+    }
+}
+
+/// Lists the imports of `module` unsupported by the sp-wasm sandbox
+pub fn analyze(module: &WasmModule) -> Vec<Incompatibility> {
+    module
+        .imports()
+        .filter(|import| import.kind == ExternalKind::Function)
+        .filter_map(|import| {
+            categorize(&import.name).map(|category| Incompatibility {
+                import: import.clone(),
+                category,
+            })
+        })
+        .collect()
+}
+
+/// Lists the imports of the Wasm module in `binary` unsupported by the
+/// sp-wasm sandbox
+pub fn analyze_binary(binary: &GWasmBinary<'_>) -> Result<Vec<Incompatibility>> {
+    let wasm = binary.wasm.read()?;
+    let module = WasmModule::parse(&wasm)?;
+    Ok(analyze(&module))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wasm::test::module;
+
+    fn categories(imports: &[&str]) -> Vec<Category> {
+        let module = WasmModule::parse(&module(imports, &["main"])).unwrap();
+        analyze(&module)
+            .into_iter()
+            .map(|incompatibility| incompatibility.category)
+            .collect()
+    }
+
+    #[test]
+    fn compatible_imports() {
+        let imports = [
+            "___syscall5",
+            "___syscall146",
+            "_fd_write",
+            "__syscall_openat",
+            "_pthread_mutex_lock",
+            "_emscripten_memcpy_big",
+        ];
+        assert!(categories(&imports).is_empty());
+    }
+
+    #[test]
+    fn incompatible_imports() {
+        assert_eq!(
+            categories(&[
+                "___syscall102",
+                "__syscall_connect",
+                "_pthread_create",
+                "__pthread_create_js",
+                "_emscripten_futex_wait",
+                "___syscall2",
+                "_execve",
+                "__syscall_symlink",
+                "___syscall83",
+            ]),
+            vec![
+                Category::Networking,
+                Category::Networking,
+                Category::Threads,
+                Category::Threads,
+                Category::Threads,
+                Category::Processes,
+                Category::Processes,
+                Category::Filesystem,
+                Category::Filesystem,
+            ]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! SHA-256 digests of the task's input and output files
+//!
+//! [`TaskBuilder::build`] records the [`Digest`] of every file in the task's
+//! input dir in the workspace sidecar, and [`ComputedSubtask`] exposes the
+//! digests of the subtask's output files.
+//!
+//! [`TaskBuilder::build`]: ../task/struct.TaskBuilder.html#method.build
+//! [`Digest`]: struct.Digest.html
+//! [`ComputedSubtask`]: ../task/struct.ComputedSubtask.html
+use super::{
+    error::{Error, FileContext},
+    Result,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File},
+    io,
+    path::Path,
+    str::FromStr,
+};
+
+/// SHA-256 digest of a file's contents
+///
+/// `Digest` is displayed, parsed and serialized as a lowercase hex string.
+///
+/// # Example:
+/// ```rust
+/// use gwasm_api::digest::Digest;
+/// use std::str::FromStr;
+///
+/// let digest = Digest::of_bytes(b"");
+/// assert_eq!(
+///     digest.to_string(),
+///     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+/// );
+/// assert_eq!(Digest::from_str(&digest.to_string()).unwrap(), digest);
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Digest([u8; 32]);
+
+impl Digest {
+    /// Computes the digest of `bytes`
+    pub fn of_bytes(bytes: &[u8]) -> Self {
+        Self(Sha256::digest(bytes).into())
+    }
+
+    /// Computes the digest of the contents of the file at `path`, without
+    /// loading the whole file into memory
+    pub fn of_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path).file_context(path)?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher).file_context(path)?;
+        Ok(Self(hasher.finalize().into()))
+    }
+
+    /// Raw bytes of the digest
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Digest({})", self)
+    }
+}
+
+impl FromStr for Digest {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        if value.len() != 64 || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Error::InvalidDigest(value.to_owned()));
+        }
+        let mut bytes = [0u8; 32];
+        for (byte, hex) in bytes.iter_mut().zip(value.as_bytes().chunks(2)) {
+            let nibble = |b: u8| (b as char).to_digit(16).unwrap_or(0) as u8;
+            *byte = nibble(hex[0]) << 4 | nibble(hex[1]);
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl Serialize for Digest {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Digest {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        let value = String::deserialize(d)?;
+        Self::from_str(&value).map_err(de::Error::custom)
+    }
+}
+
+/// Computes the digests of all files within `dir`, recursively
+///
+/// The files are keyed by their path relative to `dir`, with components
+/// separated by `/` regardless of the platform.
+pub(crate) fn digest_dir(dir: &Path) -> Result<BTreeMap<String, Digest>> {
+    let mut digests = BTreeMap::new();
+    let mut pending = vec![(dir.to_owned(), String::new())];
+    while let Some((path, prefix)) = pending.pop() {
+        for entry in fs::read_dir(&path).file_context(&path)? {
+            let entry = entry.file_context(&path)?;
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            let file_type = entry.file_type().file_context(entry.path())?;
+            if file_type.is_dir() {
+                pending.push((entry.path(), format!("{}/", name)));
+            } else {
+                digests.insert(name, Digest::of_file(entry.path())?);
+            }
+        }
+    }
+    Ok(digests)
+}
+
+/// Recomputes the digests of the files within `dir`, checking that they
+/// match `expected`
+pub(crate) fn verify_dir(dir: &Path, expected: &BTreeMap<String, Digest>) -> Result<()> {
+    for (name, expected) in expected {
+        let path = name.split('/').fold(dir.to_owned(), |path, c| path.join(c));
+        let actual = Digest::of_file(&path)?;
+        if actual != *expected {
+            return Err(Error::DigestMismatch {
+                path,
+                expected: *expected,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn invalid_digests() {
+        assert!(Digest::from_str("").is_err());
+        assert!(Digest::from_str(&"0".repeat(63)).is_err());
+        assert!(Digest::from_str(&"g".repeat(64)).is_err());
+        assert!(Digest::from_str(&"+f".repeat(32)).is_err());
+        assert!(Digest::from_str(&"é".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn dir_digests() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("subtask_0")).unwrap();
+        fs::write(dir.path().join("subtask_0").join("in0"), b"input").unwrap();
+        fs::write(dir.path().join("app.js"), b"js").unwrap();
+
+        let digests = digest_dir(dir.path()).unwrap();
+        assert_eq!(
+            digests.keys().collect::<Vec<_>>(),
+            vec!["app.js", "subtask_0/in0"]
+        );
+        assert_eq!(digests["subtask_0/in0"], Digest::of_bytes(b"input"));
+        assert!(verify_dir(dir.path(), &digests).is_ok());
+
+        fs::write(dir.path().join("subtask_0").join("in0"), b"tampered").unwrap();
+        match verify_dir(dir.path(), &digests) {
+            Err(Error::DigestMismatch { actual, .. }) => {
+                assert_eq!(actual, Digest::of_bytes(b"tampered"))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -1,5 +1,11 @@
 //! Errors that can be returned by the library
+use crate::compat::Incompatibility;
+use crate::digest::Digest;
+use crate::golem::SubtaskFailure;
+use crate::redundancy::Disagreement;
+use crate::validation::ValidationError;
 use actix::MailboxError;
+use actix_wamp::ErrorKind;
 use std::io;
 use std::path::{Path, PathBuf};
 
@@ -22,21 +28,98 @@ pub enum Error {
     FileError(io::Error, PathBuf),
 
     /// Wraps Golem's `actix_wamp::Error` error
-    #[error("internal Golem error: {0}")]
-    WampError(actix_wamp::Error),
+    #[error("internal Golem error{}: {error}", for_task(.task_id))]
+    WampError {
+        /// The wrapped error
+        error: actix_wamp::Error,
+        /// ID of the task the failed call concerned, if any
+        task_id: Option<String>,
+    },
 
     /// Wraps Golem RPC's `golem_rpc_api::Error` error
-    #[error("internal Golem error: {0}")]
-    GolemRPCError(golem_rpc_api::Error),
+    #[error("internal Golem error{}: {error}", for_task(.task_id))]
+    GolemRPCError {
+        /// The wrapped error
+        error: golem_rpc_api::Error,
+        /// ID of the task the failed call concerned, if any
+        task_id: Option<String>,
+    },
 
-    /// Wraps `chrono::ParseError` error
-    #[error("error parsing Timeout value: {0}")]
-    ChronoError(#[from] chrono::ParseError),
+    /// Wraps errors generated by a [`Codec`](../codec/trait.Codec.html)
+    #[error("error encoding or decoding subtask data: {0}")]
+    CodecError(Box<dyn std::error::Error + Send + Sync>),
+
+    /// Error when a computed subtask has no output to decode
+    #[error("no output for subtask {0}")]
+    MissingOutput(String),
+
+    /// Error when a gWasm binary failed [validation](../validation/index.html)
+    #[error("invalid gWasm binary: {0}")]
+    ValidationError(#[from] ValidationError),
+
+    /// Error when a gWasm binary imports functionality unsupported by the
+    /// sp-wasm sandbox (see [`compat`](../compat/index.html))
+    #[error("gWasm binary incompatible with sp-wasm sandbox: {}", list(.0))]
+    IncompatibleBinary(Vec<Incompatibility>),
+
+    /// Error when the name of a shared input is not a plain file name, or
+    /// collides with another file or dir in the task's input dir
+    #[error("invalid shared input name \"{0}\"")]
+    InvalidSharedInputName(String),
+
+    /// Error when the name of a subtask is not a plain file name, or
+    /// collides with another subtask, or file in the task's input dir
+    #[error("invalid subtask name \"{0}\"")]
+    InvalidSubtaskName(String),
+
+    /// Error when a string is not a valid hex-encoded SHA-256 digest
+    #[error("invalid SHA-256 digest \"{0}\"")]
+    InvalidDigest(String),
+
+    /// Error when the contents of a file do not match its recorded digest
+    #[error("{path}: digest mismatch: expected {expected}, found {actual}")]
+    DigestMismatch {
+        /// Path to the offending file
+        path: PathBuf,
+        /// Digest recorded when the task was built
+        expected: Digest,
+        /// Digest of the file's current contents
+        actual: Digest,
+    },
+
+    /// Error when the replicas of some subtasks did not agree on their outputs
+    #[error("replicas disagree on the outputs of subtasks: {}", list(.0))]
+    VerificationFailed(Vec<Disagreement>),
+
+    /// Error when a [task spec](../spec/index.html) cannot be parsed, or has
+    /// an invalid value
+    #[error("invalid task spec: {}{reason}", at_field(.field))]
+    InvalidSpec {
+        /// Path to the offending field, such as `subtasks[1].input`, if known
+        field: Option<String>,
+        /// What is wrong with the spec
+        reason: String,
+    },
+
+    /// Error when running a subtask locally failed, e.g. during
+    /// [calibration](../calibration/index.html)
+    #[error("local run failed: {0}")]
+    LocalRunFailed(String),
 
     /// Received Ctrl-C interrupt
     #[error("received Ctrl-C interrupt")]
     KeyboardInterrupt,
 
+    /// Error when a string is not a valid [`Timeout`](../timeout/struct.Timeout.html)
+    /// value
+    #[error("invalid timeout \"{value}\": {reason}")]
+    InvalidTimeout {
+        /// The offending value
+        value: String,
+        /// What is wrong with the value
+        reason: &'static str,
+    },
+
     /// Error generated when trying to create a zero [`Timeout`](../timeout/struct.Timeout.html)
     /// value for a Golem Task
     #[error("zero timeout \"00:00:00\" is forbidden")]
@@ -44,31 +127,260 @@ pub enum Error {
 
     /// Error when no TaskInfo is received when polling for task progress
     /// in [`poll_task_progress`](../golem/fn.poll_task_progress.html)
-    #[error("empty TaskInfo received from Golem")]
-    EmptyTaskInfo,
+    #[error("empty TaskInfo received from Golem for task {task_id}")]
+    EmptyTaskInfo {
+        /// ID of the polled task
+        task_id: String,
+    },
 
     /// Error when no progress can be extracted from TaskInfo
-    #[error("empty progress in TaskInfo")]
-    EmptyProgress,
+    #[error("empty progress in TaskInfo for task {task_id}")]
+    EmptyProgress {
+        /// ID of the polled task
+        task_id: String,
+    },
+
+    /// Error when no cost can be extracted from TaskInfo of a task with
+    /// a hard spending cap, in which case the task is aborted
+    #[error("empty cost in TaskInfo for task {task_id} with a hard cap")]
+    EmptyCost {
+        /// ID of the aborted task
+        task_id: String,
+    },
 
     /// Error when gWasm task was aborted externally
-    #[error("task aborted externally")]
-    TaskAborted,
+    #[error("task {task_id} aborted externally")]
+    TaskAborted {
+        /// ID of the aborted task
+        task_id: String,
+    },
 
     /// Error when gWasm task timed out
-    #[error("task timed out")]
-    TaskTimedOut,
+    #[error("task {task_id} timed out{}", failed_subtasks(.failures))]
+    TaskTimedOut {
+        /// ID of the timed out task
+        task_id: String,
+        /// Diagnostics of the subtasks which failed on providers
+        failures: Vec<SubtaskFailure>,
+    },
+
+    /// Error when a subtask produced no output after failing on providers
+    #[error("subtask {subtask} failed: {}", list(.failures))]
+    SubtaskFailed {
+        /// Subtask's name
+        subtask: String,
+        /// Diagnostics of the subtask's failures on providers
+        failures: Vec<SubtaskFailure>,
+    },
+
+    /// Error when the estimated cost of a gWasm task exceeds its budget
+    #[error("estimated task cost {estimated} exceeds budget {budget}")]
+    BudgetExceeded {
+        /// Estimated cost of the task
+        estimated: f64,
+        /// Configured budget of the task
+        budget: f64,
+    },
+
+    /// Error when the amount spent on a gWasm task passed its hard cap,
+    /// in which case the task is aborted
+    #[error("task {task_id} spending {spent} passed hard cap {cap}")]
+    BudgetCapExceeded {
+        /// ID of the aborted task
+        task_id: String,
+        /// Amount spent on the task so far
+        spent: f64,
+        /// Configured hard cap of the task
+        cap: f64,
+    },
+}
+
+impl Error {
+    /// ID of the Golem task the error concerns, if known
+    pub fn task_id(&self) -> Option<&str> {
+        match self {
+            Self::WampError { task_id, .. } | Self::GolemRPCError { task_id, .. } => {
+                task_id.as_deref()
+            }
+            Self::EmptyTaskInfo { task_id }
+            | Self::EmptyProgress { task_id }
+            | Self::EmptyCost { task_id }
+            | Self::TaskAborted { task_id }
+            | Self::TaskTimedOut { task_id, .. }
+            | Self::BudgetCapExceeded { task_id, .. } => Some(task_id),
+            _ => None,
+        }
+    }
+
+    /// Name of the subtask the error concerns, if it concerns a single subtask
+    pub fn subtask(&self) -> Option<&str> {
+        match self {
+            Self::MissingOutput(subtask) | Self::SubtaskFailed { subtask, .. } => Some(subtask),
+            Self::VerificationFailed(disagreements) if disagreements.len() == 1 => {
+                Some(&disagreements[0].subtask)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the error is transient, i.e., retrying the failed
+    /// operation as is may succeed
+    ///
+    /// Transient errors are caused by connectivity problems, Golem not
+    /// reporting the task's status yet, or misbehaving providers. All other
+    /// errors are permanent, and require changing the task or its
+    /// configuration first.
+    ///
+    /// Errors of calls to Golem are only transient if the connection to Golem
+    /// failed or was closed. Errors reported by Golem itself, e.g., when it
+    /// rejects a task's manifest or doesn't know the task's ID, are permanent.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::WampError { error, .. } => is_transient_wamp(error),
+            Self::GolemRPCError { error, .. } => is_transient_rpc(error),
+            Self::MailboxError(_)
+            | Self::EmptyTaskInfo { .. }
+            | Self::EmptyProgress { .. }
+            | Self::TaskTimedOut { .. }
+            | Self::VerificationFailed(_) => true,
+            Self::IOError(err) | Self::FileError(err, _) => is_transient_io(err),
+            Self::CodecError(_)
+            | Self::MissingOutput(_)
+            | Self::SubtaskFailed { .. }
+            | Self::ValidationError(_)
+            | Self::IncompatibleBinary(_)
+            | Self::InvalidSharedInputName(_)
+            | Self::InvalidSubtaskName(_)
+            | Self::InvalidDigest(_)
+            | Self::DigestMismatch { .. }
+            | Self::InvalidSpec { .. }
+            | Self::LocalRunFailed(_)
+            | Self::KeyboardInterrupt
+            | Self::InvalidTimeout { .. }
+            | Self::ZeroTimeoutError
+            | Self::TaskAborted { .. }
+            | Self::BudgetExceeded { .. }
+            | Self::BudgetCapExceeded { .. }
+            | Self::EmptyCost { .. } => false,
+        }
+    }
+
+    /// Returns `true` if the error is permanent, i.e., not [transient]
+    ///
+    /// [transient]: enum.Error.html#method.is_transient
+    pub fn is_permanent(&self) -> bool {
+        !self.is_transient()
+    }
+
+    /// Attaches `task_id` to errors of calls to Golem which concern a task
+    pub(crate) fn with_task_id(self, id: &str) -> Self {
+        match self {
+            Self::WampError {
+                error,
+                task_id: None,
+            } => Self::WampError {
+                error,
+                task_id: Some(id.to_owned()),
+            },
+            Self::GolemRPCError {
+                error,
+                task_id: None,
+            } => Self::GolemRPCError {
+                error,
+                task_id: Some(id.to_owned()),
+            },
+            err => err,
+        }
+    }
+}
+
+fn is_transient_io(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+    )
+}
+
+// connection and transport errors are transient, as are the router closing
+// the session, while errors returned by Golem's procedures are permanent
+fn is_transient_wamp(err: &actix_wamp::Error) -> bool {
+    match err {
+        actix_wamp::Error::ConnectionClosed
+        | actix_wamp::Error::MailboxError(_)
+        | actix_wamp::Error::WsClientError(_)
+        | actix_wamp::Error::ActixProtocolErorr(_) => true,
+        actix_wamp::Error::WampError(err) => matches!(
+            err.code,
+            ErrorKind::SystemShutdown | ErrorKind::CloseRealm | ErrorKind::GoodbyeAndOut
+        ),
+        actix_wamp::Error::ProcessingError { cause, .. } => cause
+            .downcast_ref::<io::Error>()
+            .into_iter()
+            .any(is_transient_io),
+        _ => false,
+    }
+}
+
+fn is_transient_rpc(err: &golem_rpc_api::Error) -> bool {
+    match err {
+        golem_rpc_api::Error::WampError(err) => is_transient_wamp(err),
+        golem_rpc_api::Error::IO(err) => is_transient_io(err),
+        _ => false,
+    }
+}
+
+fn failed_subtasks(failures: &[SubtaskFailure]) -> String {
+    if failures.is_empty() {
+        String::new()
+    } else {
+        format!("; failed subtasks: {}", list(failures))
+    }
+}
+
+fn at_field(field: &Option<String>) -> String {
+    field
+        .as_ref()
+        .map(|field| format!("{}: ", field))
+        .unwrap_or_default()
+}
+
+fn for_task(task_id: &Option<String>) -> String {
+    task_id
+        .as_ref()
+        .map(|task_id| format!(" for task {}", task_id))
+        .unwrap_or_default()
+}
+
+fn list<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl From<actix_wamp::Error> for Error {
     fn from(err: actix_wamp::Error) -> Self {
-        Self::WampError(err)
+        Self::WampError {
+            error: err,
+            task_id: None,
+        }
     }
 }
 
 impl From<golem_rpc_api::Error> for Error {
     fn from(err: golem_rpc_api::Error) -> Self {
-        Self::GolemRPCError(err)
+        Self::GolemRPCError {
+            error: err,
+            task_id: None,
+        }
     }
 }
 
@@ -81,3 +393,68 @@ impl<T, P: AsRef<Path>> FileContext<T, P> for io::Result<T> {
         self.map_err(|e| Error::FileError(e, path.as_ref().to_owned()))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classification() {
+        let err = Error::TaskTimedOut {
+            task_id: "1234".into(),
+            failures: Vec::new(),
+        };
+        assert_eq!(err.task_id(), Some("1234"));
+        assert!(err.is_transient());
+
+        let err = Error::from(io::Error::from(io::ErrorKind::ConnectionReset));
+        assert!(err.is_transient());
+        let err = io::Error::from(io::ErrorKind::NotFound);
+        let err: Result<()> = Err(err).file_context("in");
+        assert!(err.unwrap_err().is_permanent());
+
+        let err = Error::MissingOutput("subtask_0".into());
+        assert_eq!(err.subtask(), Some("subtask_0"));
+        assert_eq!(err.task_id(), None);
+        assert!(err.is_permanent());
+    }
+
+    #[test]
+    fn rpc_classification() {
+        let transient = vec![
+            Error::from(actix_wamp::Error::ConnectionClosed),
+            Error::from(actix_wamp::Error::WsClientError(
+                "connection refused".into(),
+            )),
+            Error::from(actix_wamp::Error::from(io::Error::from(
+                io::ErrorKind::ConnectionReset,
+            ))),
+            Error::from(golem_rpc_api::Error::IO(io::Error::from(
+                io::ErrorKind::ConnectionRefused,
+            ))),
+            Error::from(golem_rpc_api::Error::WampError(
+                actix_wamp::Error::ConnectionClosed,
+            )),
+        ];
+        for err in transient {
+            assert!(err.is_transient(), "{:?}", err);
+        }
+
+        let permanent = vec![
+            Error::from(actix_wamp::Error::wamp_error(
+                ErrorKind::InvalidArgument,
+                "invalid task definition".into(),
+            )),
+            Error::from(actix_wamp::Error::from(io::Error::from(
+                io::ErrorKind::InvalidData,
+            ))),
+            Error::from(golem_rpc_api::Error::Other("unknown task".into())),
+            Error::from(golem_rpc_api::Error::WampError(
+                actix_wamp::Error::wamp_error(ErrorKind::NoSuchProcedure, "comp.task".into()),
+            )),
+        ];
+        for err in permanent {
+            assert!(err.is_permanent(), "{:?}", err);
+        }
+    }
+}
@@ -1,20 +1,26 @@
 //! Convenience async functions for creating gWasm tasks, connecting to a
 //! Golem instance, and listening for task's progress as it's computed
 //! on Golem.
-use super::error::{Error, Result};
+use super::error::{Error, FileContext, Result};
 use super::task::{ComputedTask, Task};
+use super::workspace;
 use super::{Net, ProgressUpdate};
 use actix::{Actor, ActorContext, Context, Handler, Message};
 use actix_wamp::RpcEndpoint;
 use futures::future::FutureExt;
 use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use futures::{pin_mut, select};
-use golem_rpc_api::comp::{AsGolemComp, TaskStatus as GolemTaskStatus};
+use golem_rpc_api::comp::{AsGolemComp, TaskInfo, TaskStatus as GolemTaskStatus};
 use golem_rpc_api::connect_to_app;
-use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::{signal, time};
 
@@ -45,16 +51,58 @@ where
 {
     let (endpoint, task_id) =
         create_task(&datadir.into(), &address.into(), port, net, task.clone()).await?;
+    track_task(endpoint, task_id, task, progress_handler, polling_interval).await
+}
+
+/// A convenience function for tracking a gWasm [`Task`] created on Golem with
+/// [`create_task`] until it's computed
+///
+/// This is the second half of [`compute`], for when the task's ID is needed
+/// before the task is computed, e.g., to store it. The task's progress is
+/// reported to `progress_handler`, and the task is aborted on Golem if its
+/// budget cap is exceeded, or on Ctrl-C.
+///
+/// [`Task`]: ../task/struct.Task.html
+/// [`create_task`]: fn.create_task.html
+/// [`compute`]: fn.compute.html
+pub async fn track_task(
+    endpoint: impl Clone + Send + RpcEndpoint + 'static,
+    task_id: String,
+    task: Task,
+    progress_handler: impl ProgressUpdate + 'static,
+    polling_interval: Option<Duration>,
+) -> Result<ComputedTask> {
+    let abort = |err: Error| {
+        let (endpoint, task_id) = (endpoint.clone(), task_id.clone());
+        async move {
+            abort_task(&endpoint, task_id).await?;
+            Err(err)
+        }
+    };
+    let budget = task.budget();
+    let budget_cap = task.budget_cap();
+    let metadata = Arc::new(task.metadata().clone());
     let poll_stream = poll_task_progress(endpoint.clone(), task_id.clone(), polling_interval);
+    let fold_task_id = task_id.clone();
     let progress = poll_stream
         .try_fold(
             ProgressActor::new(progress_handler).start(),
-            |addr, task_status| async move {
-                addr.send(Update {
-                    progress: task_status.progress,
-                })
-                .await?;
-                Ok(addr)
+            move |addr, task_status| {
+                let task_id = fold_task_id.clone();
+                let metadata = metadata.clone();
+                async move {
+                    addr.send(Update {
+                        progress: task_status.progress,
+                        spent: task_status.spent,
+                        budget,
+                        metadata,
+                    })
+                    .await?;
+                    if let Some(cap) = budget_cap {
+                        check_budget_cap(task_id, task_status.spent, cap)?;
+                    }
+                    Ok(addr)
+                }
             },
         )
         .fuse();
@@ -65,24 +113,65 @@ where
     select! {
         maybe_ctrlc = ctrlc => {
             maybe_ctrlc?;
-            endpoint.as_golem_comp().abort_task(task_id).await?;
-            Err(Error::KeyboardInterrupt)
+            abort(Error::KeyboardInterrupt).await
         }
         maybe_addr = progress => {
-            let addr = maybe_addr?;
+            let addr = match maybe_addr {
+                Err(err @ Error::BudgetCapExceeded { .. }) | Err(err @ Error::EmptyCost { .. }) => {
+                    return abort(err).await
+                }
+                Err(Error::TaskTimedOut { task_id, .. }) => {
+                    let infos = get_subtask_infos(&endpoint, task_id.clone())
+                        .await
+                        .unwrap_or_default();
+                    return timed_out(task, task_id, &infos);
+                }
+                maybe_addr => maybe_addr?,
+            };
             addr.send(Finish).await?;
-            let task: ComputedTask = task.try_into()?;
-            Ok(task)
+            // diagnostics are best effort, and shouldn't fail an otherwise
+            // successful task
+            let infos = get_subtask_infos(&endpoint, task_id)
+                .await
+                .unwrap_or_default();
+            collect(task, &infos)
         }
     }
 }
 
+/// Converts the computed `task` into a [`ComputedTask`], attaching the
+/// subtask diagnostics from `infos`
+///
+/// [`ComputedTask`]: ../task/struct.ComputedTask.html
+fn collect(mut task: Task, infos: &[Value]) -> Result<ComputedTask> {
+    task.set_subtask_failures(infos.iter().filter_map(subtask_failure).collect());
+    task.set_subtask_nodes(infos.iter().filter_map(subtask_node).collect());
+    task.try_into()
+}
+
+/// Handles `task` timing out on Golem: with partial results, the subtasks
+/// computed before the timeout are collected, and the rest are returned as
+/// failed; otherwise, the task fails with [`Error::TaskTimedOut`]
+///
+/// [`Error::TaskTimedOut`]: ../error/enum.Error.html#variant.TaskTimedOut
+fn timed_out(task: Task, task_id: String, infos: &[Value]) -> Result<ComputedTask> {
+    if task.has_partial_results() {
+        return collect(task, infos);
+    }
+    let failures = infos.iter().filter_map(subtask_failure).collect();
+    Err(Error::TaskTimedOut { task_id, failures })
+}
+
 /// A convenience function for creating a gWasm [`Task`] on Golem
 ///
 /// This function returns to necessary components to track the `Task` on Golem Network:
 /// 1) an object implementing [`RpcEndpoint`] trait, 2) created `Task`'s ID as `String`.
 ///
+/// Before connecting to Golem, the `Task`'s estimated cost is checked against
+/// its budget (see [`Task::check_budget`]).
+///
 /// [`Task`]: ../task/struct.Task.html
+/// [`Task::check_budget`]: ../task/struct.Task.html#method.check_budget
 /// [`RpcEndpoint`]:
 /// https://golemfactory.github.io/golem-client/latest/actix_wamp/trait.RpcEndpoint.html
 pub async fn create_task(
@@ -92,11 +181,118 @@ pub async fn create_task(
     net: Net,
     task: Task,
 ) -> Result<(impl Clone + Send + RpcEndpoint, String)> {
-    let endpoint = connect_to_app(datadir, Some(net), Some((address, port))).await?;
-    let task_id = endpoint.as_golem_comp().create_task(json!(task)).await?;
+    task.check_budget()?;
+    let endpoint = connect(datadir, address, port, net).await?;
+    let task_id = endpoint
+        .as_golem_comp()
+        .create_task(task.manifest())
+        .await?;
     Ok((endpoint, task_id))
 }
 
+/// A dry run of [`compute`], which prepares a gWasm [`Task`] for Golem without
+/// connecting to it
+///
+/// The `Task`'s estimated cost is checked against its budget, as in
+/// [`create_task`], and its JSON manifest, exactly as it would be sent to Golem,
+/// is written to `manifest_path`.
+///
+/// # Example:
+/// ```
+/// use gwasm_api::golem::dry_run;
+/// use gwasm_api::task::{GWasmBinary, TaskBuilder};
+/// use tempfile::tempdir;
+///
+/// let binary = GWasmBinary::new(Vec::new(), Vec::new());
+/// let workspace = tempdir().unwrap();
+/// let task = TaskBuilder::try_new(&workspace, binary)
+///     .unwrap()
+///     .push_subtask_data(vec![0u8; 10])
+///     .push_subtask_data(vec![1u8; 10])
+///     .build()
+///     .unwrap();
+///
+/// let report = dry_run(&task, workspace.path().join("manifest.json")).unwrap();
+/// assert_eq!(report.subtasks, 2);
+/// assert_eq!(report.input_bytes, 20);
+/// ```
+///
+/// [`compute`]: fn.compute.html
+/// [`create_task`]: fn.create_task.html
+/// [`Task`]: ../task/struct.Task.html
+pub fn dry_run<P: AsRef<Path>>(task: &Task, manifest_path: P) -> Result<DryRun> {
+    let manifest_path = manifest_path.as_ref();
+    task.check_budget()?;
+    let file = File::create(manifest_path).file_context(manifest_path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, &task.manifest())
+        .map_err(|e| Error::CodecError(e.into()))?;
+    writer.flush().file_context(manifest_path)?;
+
+    Ok(DryRun {
+        manifest_path: manifest_path.to_owned(),
+        subtasks: task.options().subtasks().count(),
+        input_bytes: workspace::dir_size(task.options().input_dir_path())?,
+        estimated_cost: task.estimated_cost(),
+    })
+}
+
+/// Summary of a gWasm [`Task`] prepared by [`dry_run`]
+///
+/// [`Task`]: ../task/struct.Task.html
+/// [`dry_run`]: fn.dry_run.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct DryRun {
+    /// Path to the written JSON manifest
+    pub manifest_path: PathBuf,
+    /// Number of subtasks which would be sent to Golem, including replicas
+    pub subtasks: usize,
+    /// Total size of the files in the task's input dir, in bytes
+    pub input_bytes: u64,
+    /// [Estimated cost] of the task
+    ///
+    /// [Estimated cost]: ../task/struct.Task.html#method.estimated_cost
+    pub estimated_cost: f64,
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} subtasks, {} input bytes, estimated cost {}; manifest written to {}",
+            self.subtasks,
+            self.input_bytes,
+            self.estimated_cost,
+            self.manifest_path.display()
+        )
+    }
+}
+
+/// A convenience function for connecting to a Golem instance, e.g., to
+/// track or abort a gWasm [`Task`] created earlier
+///
+/// [`Task`]: ../task/struct.Task.html
+pub async fn connect(
+    datadir: &Path,
+    address: &str,
+    port: u16,
+    net: Net,
+) -> Result<impl Clone + Send + RpcEndpoint> {
+    let endpoint = connect_to_app(datadir, Some(net), Some((address, port))).await?;
+    Ok(endpoint)
+}
+
+/// A convenience function for aborting a gWasm [`Task`] on Golem
+///
+/// [`Task`]: ../task/struct.Task.html
+pub async fn abort_task(endpoint: &(impl RpcEndpoint + 'static), task_id: String) -> Result<()> {
+    endpoint
+        .as_golem_comp()
+        .abort_task(task_id.clone())
+        .await
+        .map_err(|e| Error::from(e).with_task_id(&task_id))
+}
+
 /// A convenience function for polling gWasm [`Task`]'s computation progress on Golem
 ///
 /// This function returns an async [`Stream`] which can be asynchronously
@@ -112,24 +308,35 @@ pub fn poll_task_progress(
     polling_interval: Option<Duration>,
 ) -> impl Stream<Item = Result<TaskStatus>> {
     stream::try_unfold(TaskState::new(endpoint, task_id), |state| async move {
+        let task_id = state.task_id.clone();
         if let Some(status) = state.task_status.status {
             match status {
                 GolemTaskStatus::Finished => return Ok(None),
-                GolemTaskStatus::Aborted => return Err(Error::TaskAborted),
-                GolemTaskStatus::Timeout => return Err(Error::TaskTimedOut),
+                GolemTaskStatus::Aborted => return Err(Error::TaskAborted { task_id }),
+                GolemTaskStatus::Timeout => {
+                    return Err(Error::TaskTimedOut {
+                        task_id,
+                        failures: Vec::new(),
+                    })
+                }
                 _ => {}
             }
         }
 
-        let mut next_state = TaskState::new(state.endpoint.clone(), state.task_id.clone());
+        let mut next_state = TaskState::new(state.endpoint.clone(), task_id.clone());
         let task_info = state
             .endpoint
             .as_golem_comp()
-            .get_task(state.task_id.clone())
-            .await?;
-        let task_info = task_info.ok_or(Error::EmptyTaskInfo)?;
+            .get_task(task_id.clone())
+            .await
+            .map_err(|e| Error::from(e).with_task_id(&task_id))?;
+        let task_info = task_info.ok_or_else(|| Error::EmptyTaskInfo {
+            task_id: task_id.clone(),
+        })?;
+        next_state.task_status.spent = task_cost(&task_info);
         next_state.task_status.status = Some(task_info.status);
-        next_state.task_status.progress = task_info.progress.ok_or(Error::EmptyProgress)?;
+        next_state.task_status.progress =
+            task_info.progress.ok_or(Error::EmptyProgress { task_id })?;
         Ok(Some((next_state.task_status.clone(), next_state)))
     })
     .zip(time::interval(
@@ -138,8 +345,159 @@ pub fn poll_task_progress(
     .map(|(x, _)| x)
 }
 
+/// A convenience function for fetching diagnostics of the subtasks of a
+/// gWasm [`Task`] which failed or timed out on providers
+///
+/// [`Task`]: ../task/struct.Task.html
+pub async fn get_subtask_failures(
+    endpoint: &(impl RpcEndpoint + 'static),
+    task_id: String,
+) -> Result<Vec<SubtaskFailure>> {
+    let infos = get_subtask_infos(endpoint, task_id).await?;
+    Ok(infos.iter().filter_map(subtask_failure).collect())
+}
+
+/// A convenience function for fetching the names of the provider nodes
+/// which computed the subtasks of a gWasm [`Task`], keyed by the subtasks'
+/// names
+///
+/// Only the subtasks which Golem reports as finished, along with the
+/// provider which computed them, are included.
+///
+/// [`Task`]: ../task/struct.Task.html
+pub async fn get_subtask_nodes(
+    endpoint: &(impl RpcEndpoint + 'static),
+    task_id: String,
+) -> Result<BTreeMap<String, String>> {
+    let infos = get_subtask_infos(endpoint, task_id).await?;
+    Ok(infos.iter().filter_map(subtask_node).collect())
+}
+
+async fn get_subtask_infos(
+    endpoint: &(impl RpcEndpoint + 'static),
+    task_id: String,
+) -> Result<Vec<Value>> {
+    let subtasks = endpoint
+        .as_golem_comp()
+        .get_subtasks(task_id.clone())
+        .await
+        .map_err(|e| Error::from(e).with_task_id(&task_id))?;
+    Ok(subtasks
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|info| serde_json::to_value(info).ok())
+        .collect())
+}
+
+/// Diagnostics of a subtask which failed or timed out on a provider, as
+/// reported by Golem
+///
+/// Golem restarts failed subtasks, so a subtask may have failures and still
+/// be computed successfully in the end.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubtaskFailure {
+    /// Name of the subtask (as in [`Options::subtasks`]), if reported by Golem
+    ///
+    /// [`Options::subtasks`]: ../task/struct.Options.html#method.subtasks
+    pub subtask: Option<String>,
+    /// ID assigned to the subtask by Golem
+    pub golem_subtask_id: String,
+    /// Name of the provider node which computed the subtask, if known
+    pub node_name: Option<String>,
+    /// Status of the subtask, such as `Failure` or `Timeout`
+    pub status: String,
+    /// Standard output of the Wasm binary, if captured
+    pub stdout: Option<String>,
+    /// Standard error of the Wasm binary, if captured
+    pub stderr: Option<String>,
+}
+
+impl fmt::Display for SubtaskFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.subtask.as_ref().unwrap_or(&self.golem_subtask_id)
+        )?;
+        if let Some(node_name) = &self.node_name {
+            write!(f, " on {}", node_name)?;
+        }
+        write!(f, ": {}", self.status)?;
+        let last_line = self
+            .stderr
+            .as_ref()
+            .and_then(|s| s.trim_end().lines().last());
+        if let Some(last_line) = last_line {
+            write!(f, ": {}", last_line)?;
+        }
+        Ok(())
+    }
+}
+
+const FAILED_SUBTASK_STATUSES: &[&str] = &["Failure", "Timeout"];
+
+// the shape of the subtask info differs between versions of Golem, so its
+// fields are looked up by name; the subtask's name is only known to gWasm
+// itself, which keeps it in the subtask's extra data
+fn subtask_failure(info: &Value) -> Option<SubtaskFailure> {
+    let status = string(info, "status")?;
+    if !FAILED_SUBTASK_STATUSES.contains(&status.as_str()) {
+        return None;
+    }
+    Some(SubtaskFailure {
+        subtask: subtask_name(info),
+        golem_subtask_id: string(info, "subtask_id").unwrap_or_default(),
+        node_name: string(info, "node_name"),
+        status,
+        stdout: string(info, "stdout"),
+        stderr: string(info, "stderr"),
+    })
+}
+
+fn subtask_node(info: &Value) -> Option<(String, String)> {
+    if string(info, "status")? != "Finished" {
+        return None;
+    }
+    Some((subtask_name(info)?, string(info, "node_name")?))
+}
+
+fn subtask_name(info: &Value) -> Option<String> {
+    info.get("extra_data")
+        .and_then(|extra_data| string(extra_data, "name"))
+        .or_else(|| string(info, "name"))
+}
+
+fn string(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+}
+
+fn task_cost(task_info: &TaskInfo) -> Option<f64> {
+    task_info.cost.as_ref()?.to_string().parse().ok()
+}
+
+// the cap can't be enforced unless Golem reports the amount spent, in which
+// case the task is aborted rather than left to run past the cap unnoticed
+fn check_budget_cap(task_id: String, spent: Option<f64>, cap: f64) -> Result<()> {
+    match spent {
+        None => Err(Error::EmptyCost { task_id }),
+        Some(spent) if spent > cap => Err(Error::BudgetCapExceeded {
+            task_id,
+            spent,
+            cap,
+        }),
+        Some(_) => Ok(()),
+    }
+}
+
 struct Update {
     progress: f64,
+    spent: Option<f64>,
+    budget: Option<f64>,
+    metadata: Arc<BTreeMap<String, String>>,
 }
 
 impl Message for Update {
@@ -179,7 +537,11 @@ impl Handler<Update> for ProgressActor {
     type Result = ();
 
     fn handle(&mut self, msg: Update, _ctx: &mut Self::Context) -> Self::Result {
-        self.handler.update(msg.progress);
+        self.handler
+            .update_with_metadata(msg.progress, &msg.metadata);
+        if let Some(spent) = msg.spent {
+            self.handler.update_spent(spent, msg.budget);
+        }
     }
 }
 
@@ -218,6 +580,24 @@ where
 pub struct TaskStatus {
     status: Option<GolemTaskStatus>,
     progress: f64,
+    spent: Option<f64>,
+}
+
+impl TaskStatus {
+    /// Status of the task reported by Golem, if polled already
+    pub fn status(&self) -> Option<&GolemTaskStatus> {
+        self.status.as_ref()
+    }
+
+    /// Current progress of the task
+    pub fn progress(&self) -> f64 {
+        self.progress
+    }
+
+    /// Amount spent on the task so far, if reported by Golem
+    pub fn spent(&self) -> Option<f64> {
+        self.spent
+    }
 }
 
 impl Default for TaskStatus {
@@ -225,6 +605,151 @@ impl Default for TaskStatus {
         Self {
             status: None,
             progress: 0.0,
+            spent: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::task::{GWasmBinary, TaskBuilder};
+    use serde_json::json;
+
+    #[test]
+    fn reported_cost() {
+        let info = |cost: Value| -> TaskInfo {
+            serde_json::from_value(json!({
+                "id": "1234",
+                "status": "Computing",
+                "time_remaining": null,
+                "subtasks_count": 2,
+                "progress": 0.5,
+                "cost": cost,
+                "fee": null,
+                "estimated_cost": "2",
+                "estimated_fee": "0.1",
+            }))
+            .unwrap()
+        };
+        assert_eq!(task_cost(&info(json!("0.25"))), Some(0.25));
+        assert_eq!(task_cost(&info(json!(1.5))), Some(1.5));
+        assert_eq!(task_cost(&info(Value::Null)), None);
+    }
+
+    #[test]
+    fn budget_cap() {
+        assert!(check_budget_cap("1234".into(), Some(1.0), 1.0).is_ok());
+        match check_budget_cap("1234".into(), Some(1.5), 1.0) {
+            Err(Error::BudgetCapExceeded { spent, cap, .. }) => {
+                assert_eq!((spent, cap), (1.5, 1.0))
+            }
+            res => panic!("unexpected result: {:?}", res),
         }
+        match check_budget_cap("1234".into(), None, 1.0) {
+            Err(Error::EmptyCost { task_id }) => assert_eq!(task_id, "1234"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn failed_subtask_info() {
+        let info = json!({
+            "subtask_id": "1234",
+            "node_name": "provider",
+            "status": "Failure",
+            "stdout": "",
+            "stderr": "reading input\nRuntimeError: unreachable executed\n",
+            "extra_data": { "name": "subtask_0" },
+        });
+        let failure = subtask_failure(&info).unwrap();
+        assert_eq!(failure.subtask.as_deref(), Some("subtask_0"));
+        assert_eq!(failure.stdout, None);
+        assert_eq!(
+            failure.to_string(),
+            "subtask_0 on provider: Failure: RuntimeError: unreachable executed"
+        );
+
+        let info = json!({ "subtask_id": "1234", "status": "Finished" });
+        assert!(subtask_failure(&info).is_none());
+    }
+
+    #[test]
+    fn finished_subtask_node() {
+        let info = json!({
+            "subtask_id": "1234",
+            "node_name": "provider",
+            "status": "Finished",
+            "extra_data": { "name": "subtask_0" },
+        });
+        assert_eq!(
+            subtask_node(&info),
+            Some(("subtask_0".into(), "provider".into()))
+        );
+
+        let info = json!({ "node_name": "provider", "status": "Failure", "name": "subtask_0" });
+        assert!(subtask_node(&info).is_none());
+    }
+
+    #[test]
+    fn partial_results_on_timeout() {
+        let infos = vec![json!({
+            "subtask_id": "1234",
+            "status": "Timeout",
+            "extra_data": { "name": "subtask_1" },
+        })];
+        let build = |partial_results| {
+            let workspace = tempfile::tempdir().unwrap();
+            let binary = GWasmBinary::new(Vec::new(), Vec::new());
+            let task = TaskBuilder::try_new(&workspace, binary)
+                .unwrap()
+                .partial_results(partial_results)
+                .push_subtask_data(vec![0u8; 16])
+                .push_subtask_data(vec![1u8; 16])
+                .build()
+                .unwrap();
+            let output = task.options().output_dir_path().join("subtask_0");
+            std::fs::write(output.join("out"), b"output").unwrap();
+            (workspace, task)
+        };
+
+        let (_workspace, task) = build(false);
+        match timed_out(task, "task".into(), &infos) {
+            Err(Error::TaskTimedOut { failures, .. }) => assert_eq!(failures.len(), 1),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        let (_workspace, task) = build(true);
+        let computed_task = timed_out(task, "task".into(), &infos).unwrap();
+        assert_eq!(computed_task.subtasks.len(), 1);
+        assert_eq!(computed_task.failed_subtasks.len(), 1);
+        match &computed_task.failed_subtasks[0].error {
+            Error::SubtaskFailed { subtask, failures } => {
+                assert_eq!(subtask, "subtask_1");
+                assert_eq!(failures[0].status, "Timeout");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dry_run_manifest() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(vec![0u8; 3], vec![0u8; 5]);
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .push_subtask_data(vec![0u8; 16])
+            .push_subtask_data(vec![1u8; 16])
+            .build()
+            .unwrap();
+
+        let manifest_path = workspace.path().join("manifest.json");
+        let report = dry_run(&task, &manifest_path).unwrap();
+        assert_eq!(report.subtasks, 2);
+        assert_eq!(report.input_bytes, 40);
+        assert_eq!(report.estimated_cost, task.estimated_cost());
+        let manifest: Value =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest, json!(task));
     }
 }
@@ -0,0 +1,258 @@
+//! Sources of subtask input data
+//!
+//! A subtask's input doesn't have to be loaded into memory before the
+//! [`Task`] is built. Instead, it can be streamed from a reader, an iterator
+//! or a stream of chunks, or a file on disk. In each case, the data is
+//! written to the workspace only when [`TaskBuilder::build`] is executed,
+//! and with bounded memory.
+//!
+//! [`Task`]: ../task/struct.Task.html
+//! [`TaskBuilder::build`]: ../task/struct.TaskBuilder.html#method.build
+use super::{digest::Digest, error::FileContext, Result};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Specifies how an input file is placed in the workspace
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FileMode {
+    /// Copies the file
+    ///
+    /// Note that on filesystems supporting it (e.g., Btrfs, XFS or APFS),
+    /// the copy is made as a reflink, i.e., it shares the data with the
+    /// original file until either of them is modified.
+    Copy,
+    /// Hard-links the file, falling back to copying it if the file resides
+    /// on a different filesystem than the workspace
+    HardLink,
+}
+
+/// Source of subtask input data
+pub enum SubtaskInput {
+    /// In-memory buffer
+    Bytes(Vec<u8>),
+    /// Data read until EOF from a reader
+    Reader(Box<dyn Read + Send>),
+    /// Data produced chunk by chunk by an iterator
+    Chunks(Box<dyn Iterator<Item = Vec<u8>> + Send>),
+    /// Data stored in a file
+    File(PathBuf, FileMode),
+}
+
+impl SubtaskInput {
+    /// Writes the input data to a file at `path`
+    pub(crate) fn write_to(self, path: &Path) -> Result<()> {
+        match self {
+            Self::Bytes(data) => fs::write(path, data).file_context(path),
+            Self::Reader(mut reader) => {
+                let mut writer = create(path)?;
+                io::copy(&mut reader, &mut writer).file_context(path)?;
+                writer.flush().file_context(path)
+            }
+            Self::Chunks(chunks) => {
+                let mut writer = create(path)?;
+                for chunk in chunks {
+                    writer.write_all(&chunk).file_context(path)?;
+                }
+                writer.flush().file_context(path)
+            }
+            Self::File(source, FileMode::Copy) => {
+                fs::copy(&source, path).map(|_| ()).file_context(&source)
+            }
+            Self::File(source, FileMode::HardLink) => fs::hard_link(&source, path)
+                .or_else(|err| {
+                    if is_cross_device(&err) {
+                        fs::copy(&source, path).map(|_| ())
+                    } else {
+                        Err(err)
+                    }
+                })
+                .file_context(&source),
+        }
+    }
+}
+
+/// Returns `true` if `err` is caused by linking or renaming a file across
+/// filesystems
+fn is_cross_device(err: &io::Error) -> bool {
+    if cfg!(unix) {
+        // EXDEV
+        err.raw_os_error() == Some(18)
+    } else if cfg!(windows) {
+        // ERROR_NOT_SAME_DEVICE
+        err.raw_os_error() == Some(17)
+    } else {
+        false
+    }
+}
+
+/// Subtask input after deduplication
+pub(crate) enum Deduplicated {
+    /// First occurrence of the input
+    Unique(SubtaskInput),
+    /// Input identical to the one of the subtask with the given index
+    Duplicate(usize),
+}
+
+/// Finds identical inputs among `inputs`
+///
+/// In-memory and file inputs are compared by the SHA-256 digests of their
+/// contents, with files read in a streaming fashion. Files which can't be
+/// read are not deduplicated, leaving the error to be reported once they are
+/// written. Streamed inputs are never deduplicated since their contents are
+/// not known until they are written.
+pub(crate) fn deduplicate(inputs: Vec<SubtaskInput>) -> Vec<Deduplicated> {
+    let mut seen = HashMap::new();
+    // digests of the files already read, so that each file is read once
+    let mut files = HashMap::new();
+    inputs
+        .into_iter()
+        .enumerate()
+        .map(|(i, input)| {
+            let key = match &input {
+                SubtaskInput::Bytes(data) => Some(Digest::of_bytes(data)),
+                SubtaskInput::File(path, _) => path.canonicalize().ok().and_then(|path| {
+                    if !files.contains_key(&path) {
+                        let digest = Digest::of_file(&path).ok();
+                        files.insert(path.clone(), digest);
+                    }
+                    files[&path]
+                }),
+                _ => None,
+            };
+            match key {
+                Some(key) => match seen.get(&key) {
+                    Some(&primary) => Deduplicated::Duplicate(primary),
+                    None => {
+                        seen.insert(key, i);
+                        Deduplicated::Unique(input)
+                    }
+                },
+                None => Deduplicated::Unique(input),
+            }
+        })
+        .collect()
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(path).file_context(path)?;
+    Ok(BufWriter::new(file))
+}
+
+impl fmt::Debug for SubtaskInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bytes(data) => f.debug_tuple("Bytes").field(&data.len()).finish(),
+            Self::Reader(_) => f.debug_tuple("Reader").finish(),
+            Self::Chunks(_) => f.debug_tuple("Chunks").finish(),
+            Self::File(path, mode) => f.debug_tuple("File").field(path).field(mode).finish(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for SubtaskInput {
+    fn from(data: Vec<u8>) -> Self {
+        Self::Bytes(data)
+    }
+}
+
+impl From<&[u8]> for SubtaskInput {
+    fn from(data: &[u8]) -> Self {
+        Self::Bytes(data.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn deduplicate_inputs() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::write(&source, b"file").unwrap();
+
+        let inputs = vec![
+            SubtaskInput::from(&b"a"[..]),
+            SubtaskInput::from(&b"b"[..]),
+            SubtaskInput::from(&b"a"[..]),
+            SubtaskInput::File(source.clone(), FileMode::Copy),
+            SubtaskInput::Reader(Box::new(&b"a"[..])),
+            SubtaskInput::File(dir.path().join(".").join("source"), FileMode::Copy),
+        ];
+        let primaries: Vec<_> = deduplicate(inputs)
+            .into_iter()
+            .map(|input| match input {
+                Deduplicated::Unique(_) => None,
+                Deduplicated::Duplicate(primary) => Some(primary),
+            })
+            .collect();
+        assert_eq!(primaries, vec![None, None, Some(0), None, None, Some(3)]);
+    }
+
+    #[test]
+    fn deduplicate_files_by_content() {
+        let dir = tempdir().unwrap();
+        for (name, content) in &[("a", "same"), ("b", "same"), ("c", "other")] {
+            fs::write(dir.path().join(name), content).unwrap();
+        }
+
+        let inputs = vec![
+            SubtaskInput::File(dir.path().join("a"), FileMode::Copy),
+            SubtaskInput::File(dir.path().join("b"), FileMode::Copy),
+            SubtaskInput::File(dir.path().join("c"), FileMode::Copy),
+            SubtaskInput::from(&b"same"[..]),
+            SubtaskInput::File(dir.path().join("missing"), FileMode::Copy),
+        ];
+        let primaries: Vec<_> = deduplicate(inputs)
+            .into_iter()
+            .map(|input| match input {
+                Deduplicated::Unique(_) => None,
+                Deduplicated::Duplicate(primary) => Some(primary),
+            })
+            .collect();
+        assert_eq!(primaries, vec![None, Some(0), None, Some(0), None]);
+    }
+
+    #[test]
+    fn write_all_sources() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::write(&source, b"file").unwrap();
+
+        let inputs = vec![
+            (SubtaskInput::from(&b"bytes"[..]), &b"bytes"[..]),
+            (SubtaskInput::Reader(Box::new(&b"reader"[..])), b"reader"),
+            (
+                SubtaskInput::Chunks(Box::new(vec![b"chu".to_vec(), b"nks".to_vec()].into_iter())),
+                b"chunks",
+            ),
+            (SubtaskInput::File(source.clone(), FileMode::Copy), b"file"),
+            (SubtaskInput::File(source, FileMode::HardLink), b"file"),
+        ];
+        for (i, (input, expected)) in inputs.into_iter().enumerate() {
+            let path = dir.path().join(format!("in{}", i));
+            input.write_to(&path).unwrap();
+            assert_eq!(fs::read(&path).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn hard_link_errors() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::write(&source, b"file").unwrap();
+        let path = dir.path().join("in0");
+        fs::write(&path, b"existing").unwrap();
+
+        // only a cross-device link falls back to copying
+        let input = SubtaskInput::File(source, FileMode::HardLink);
+        assert!(input.write_to(&path).is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"existing");
+    }
+}
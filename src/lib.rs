@@ -20,10 +20,10 @@
 //! }
 //!
 //! fn main() -> Result<()> {
-//!     let binary = GWasmBinary {
-//!         js: &[0u8; 100],   // JavaScript file generated by Emscripten
-//!         wasm: &[0u8; 100], // Wasm binary generated by Emscripten
-//!     };
+//!     let binary = GWasmBinary::new(
+//!         vec![0u8; 100], // JavaScript file generated by Emscripten
+//!         vec![0u8; 100], // Wasm binary generated by Emscripten
+//!     );
 //!     let task = TaskBuilder::try_new("workspace", binary)?
 //!         .push_subtask_data(vec![0u8; 100])
 //!         .build()?;
@@ -61,6 +61,19 @@
 //! For more information about how to write gWASM apps see the
 //! [sp-wasm documentation](https://github.com/golemfactory/sp-wasm)
 //!
+//! ## Command-line tool
+//! With the `cli` feature enabled, the crate also provides the `gwasm` binary,
+//! which builds task workspaces, submits the tasks to Golem, tracks them, and
+//! collects their results, e.g.:
+//! ```shell
+//! cargo install gwasm-api --features cli
+//! gwasm build workspace --binary build/app --inputs inputs --subtask-timeout 10m
+//! gwasm submit workspace --datadir datadir
+//! gwasm watch workspace --datadir datadir
+//! gwasm collect workspace --output results
+//! ```
+//! See `gwasm help` for all subcommands and their options.
+//!
 //! ## More examples
 //! * [g-flite](https://github.com/golemfactory/g-flite) is a CLI which uses `gwasm-api`
 //!   internally
@@ -86,14 +99,26 @@
     )
 )]
 
+pub mod calibration;
+pub mod codec;
+pub mod compat;
+pub mod digest;
 pub mod error;
 pub mod golem;
+pub mod input;
+pub mod mapreduce;
+pub mod redundancy;
+pub mod spec;
 pub mod task;
 pub mod timeout;
+pub mod validation;
+pub mod wasm;
+pub mod workspace;
 
 use actix::System;
 use error::Result;
 pub use golem_rpc_api::Net;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use task::{ComputedTask, Task};
 
@@ -158,6 +183,19 @@ use task::{ComputedTask, Task};
 pub trait ProgressUpdate {
     /// Called when progress value was polled from Golem
     fn update(&self, progress: f64);
+    /// Called when progress value was polled from Golem, together with the
+    /// task's [metadata], e.g. to tell apart the tasks sharing the handler
+    ///
+    /// By default, it ignores the metadata and calls [`update`].
+    ///
+    /// [metadata]: task/struct.TaskBuilder.html#method.metadata
+    /// [`update`]: trait.ProgressUpdate.html#tymethod.update
+    fn update_with_metadata(&self, progress: f64, _metadata: &BTreeMap<String, String>) {
+        self.update(progress)
+    }
+    /// Called when the amount spent on the task was polled from Golem,
+    /// together with the task's budget (if any)
+    fn update_spent(&self, _spent: f64, _budget: Option<f64>) {}
     /// Called when progress updates started
     fn start(&self) {}
     /// Called when progress updates finished
@@ -206,10 +244,14 @@ pub mod prelude {
     //! # #![allow(unused_imports)]
     //! use gwasm_api::prelude::*;
     //! ```
+    pub use super::codec::{Codec, Json};
     pub use super::error::{Error, Result};
+    pub use super::input::{FileMode, SubtaskInput};
     pub use super::task::{
-        ComputedSubtask, ComputedTask, GWasmBinary, Options, Subtask, Task, TaskBuilder,
+        BinarySource, ComputedSubtask, ComputedTask, FailedSubtask, GWasmBinary, Options, Subtask,
+        Task, TaskBuilder,
     };
     pub use super::timeout::Timeout;
+    pub use super::workspace::WorkspacePolicy;
     pub use super::{compute, Net, ProgressUpdate};
 }
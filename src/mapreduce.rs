@@ -0,0 +1,190 @@
+//! Map/reduce style helpers built on [`TaskBuilder`] and [`ComputedTask`]
+//!
+//! Many gWasm tasks follow the same pattern: a large input is split into
+//! chunks, each chunk is computed as a separate subtask, and the outputs
+//! are then merged into the final result. The functions in this module
+//! cover this flow, encoding the chunks and decoding the outputs with
+//! a [`Codec`].
+//!
+//! The outputs are always passed to the reducer in subtask order, i.e.,
+//! in the same order as the chunks were returned by the splitter.
+//!
+//! # Example:
+//! ```rust,no_run
+//! use gwasm_api::prelude::*;
+//! use gwasm_api::mapreduce::map_reduce;
+//! use anyhow::Result;
+//!
+//! struct ProgressTracker;
+//!
+//! impl ProgressUpdate for ProgressTracker {
+//!     fn update(&self, progress: f64) {
+//!         println!("Current progress = {}", progress);
+//!     }
+//! }
+//!
+//! fn main() -> Result<()> {
+//!     let binary = GWasmBinary::new(
+//!         vec![0u8; 100], // JavaScript file generated by Emscripten
+//!         vec![0u8; 100], // Wasm binary generated by Emscripten
+//!     );
+//!     let builder = TaskBuilder::try_new("workspace", binary)?;
+//!     let numbers: Vec<u64> = (0..1000).collect();
+//!     let sum = map_reduce(
+//!         Json,
+//!         builder,
+//!         numbers,
+//!         |numbers| {
+//!             numbers
+//!                 .chunks(100)
+//!                 .map(|chunk| chunk.to_vec())
+//!                 .collect::<Vec<_>>()
+//!         },
+//!         |partial_sums: Vec<u64>| partial_sums.into_iter().sum::<u64>(),
+//!         |task| {
+//!             compute(
+//!                 "datadir",
+//!                 "127.0.0.1",
+//!                 61000,
+//!                 Net::TestNet,
+//!                 task,
+//!                 ProgressTracker,
+//!             )
+//!         },
+//!     )?;
+//!     println!("sum = {}", sum);
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`TaskBuilder`]: ../task/struct.TaskBuilder.html
+//! [`ComputedTask`]: ../task/struct.ComputedTask.html
+//! [`Codec`]: ../codec/trait.Codec.html
+use super::codec::Codec;
+use super::task::{ComputedTask, Task, TaskBuilder};
+use super::Result;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Splits `input` into chunks using `splitter`, and builds a [`Task`] with
+/// one subtask per chunk
+///
+/// Each chunk is encoded with codec `C`, and pushed as subtask data in the
+/// order returned by `splitter`.
+///
+/// [`Task`]: ../task/struct.Task.html
+pub fn split<C, T, I, It, S>(
+    _codec: C,
+    builder: TaskBuilder<'_>,
+    input: T,
+    splitter: S,
+) -> Result<Task>
+where
+    C: Codec,
+    I: Serialize,
+    It: IntoIterator<Item = I>,
+    S: FnOnce(T) -> It,
+{
+    let builder = splitter(input)
+        .into_iter()
+        .try_fold(builder, |builder, chunk| {
+            builder.push_subtask_value::<C, _>(&chunk)
+        })?;
+    builder.build()
+}
+
+/// Decodes the outputs of a [`ComputedTask`] with codec `C`, and merges
+/// them using `reducer`
+///
+/// The outputs are passed to `reducer` in subtask order. Fails with the error
+/// of the first [failed subtask], if any.
+///
+/// [`ComputedTask`]: ../task/struct.ComputedTask.html
+/// [failed subtask]: ../task/struct.ComputedTask.html#structfield.failed_subtasks
+pub fn reduce<C, O, R, F>(_codec: C, mut computed_task: ComputedTask, reducer: F) -> Result<R>
+where
+    C: Codec,
+    O: DeserializeOwned,
+    F: FnOnce(Vec<O>) -> R,
+{
+    if !computed_task.failed_subtasks.is_empty() {
+        return Err(computed_task.failed_subtasks.remove(0).error);
+    }
+    let outputs = computed_task.decode_outputs::<C, O>()?;
+    Ok(reducer(outputs))
+}
+
+/// Runs the whole map/reduce flow: [`split`]s `input` into subtasks,
+/// computes the resulting [`Task`] with `compute`, and [`reduce`]s the outputs
+///
+/// `compute` is typically a closure calling [`gwasm_api::compute`].
+///
+/// [`split`]: fn.split.html
+/// [`reduce`]: fn.reduce.html
+/// [`Task`]: ../task/struct.Task.html
+/// [`gwasm_api::compute`]: ../fn.compute.html
+pub fn map_reduce<C, T, I, It, S, O, R, F, E>(
+    codec: C,
+    builder: TaskBuilder<'_>,
+    input: T,
+    splitter: S,
+    reducer: F,
+    compute: E,
+) -> Result<R>
+where
+    C: Codec + Copy,
+    I: Serialize,
+    It: IntoIterator<Item = I>,
+    S: FnOnce(T) -> It,
+    O: DeserializeOwned,
+    F: FnOnce(Vec<O>) -> R,
+    E: FnOnce(Task) -> Result<ComputedTask>,
+{
+    let task = split(codec, builder, input, splitter)?;
+    let computed_task = compute(task)?;
+    reduce(codec, computed_task, reducer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codec::Json;
+    use crate::task::GWasmBinary;
+    use std::convert::TryFrom;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn split_and_reduce() {
+        let workspace = tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let builder = TaskBuilder::try_new(&workspace, binary).unwrap();
+        let numbers: Vec<u32> = (1..=24).collect();
+        let task = split(Json, builder, numbers, |numbers| {
+            numbers
+                .chunks(2)
+                .map(|chunk| chunk.to_vec())
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+        assert_eq!(task.options().subtasks().count(), 12);
+
+        // emulate the Wasm app computing the sum of each chunk
+        for (name, subtask) in task.options().subtasks() {
+            let input_name = subtask.exec_args().next().unwrap();
+            let input = task.options().input_dir_path().join(name).join(input_name);
+            let chunk: Vec<u32> = serde_json::from_slice(&fs::read(input).unwrap()).unwrap();
+            let output = task.options().output_dir_path().join(name).join("out");
+            fs::write(
+                output,
+                serde_json::to_vec(&chunk.iter().sum::<u32>()).unwrap(),
+            )
+            .unwrap();
+        }
+
+        let computed_task = ComputedTask::try_from(task).unwrap();
+        let sums = reduce(Json, computed_task, |sums: Vec<u32>| sums).unwrap();
+        let expected: Vec<u32> = (0..12).map(|i| 4 * i + 3).collect();
+        assert_eq!(sums, expected);
+    }
+}
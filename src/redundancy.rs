@@ -0,0 +1,283 @@
+//! Redundant computation of subtasks and verification of their results
+//!
+//! Golem providers are untrusted, so a single output of a subtask cannot be
+//! taken at face value. With [`TaskBuilder::redundancy`], every subtask is
+//! computed by several replicas, and their outputs are compared when the
+//! task is converted into a [`ComputedTask`].
+//!
+//! The replicas are just separate subtasks to Golem, which may well assign
+//! several of them to the same provider. Replicas known to be computed by
+//! the same provider are therefore never taken to agree with each other.
+//! Providers are only known for tasks computed with [`compute`], though, so
+//! verification across providers can't be guaranteed for tasks converted
+//! otherwise.
+//!
+//! [`TaskBuilder::redundancy`]: ../task/struct.TaskBuilder.html#method.redundancy
+//! [`ComputedTask`]: ../task/struct.ComputedTask.html
+//! [`compute`]: ../fn.compute.html
+use super::{digest::Digest, error::FileContext, Result};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+type Comparator = Arc<dyn Fn(&[u8], &[u8]) -> bool + Send + Sync>;
+
+/// Specifies how many times each subtask is computed, and how the outputs
+/// of the replicas are verified
+///
+/// By default, the outputs of all replicas are compared byte by byte, and
+/// have to be unanimous.
+///
+/// # Example:
+/// ```rust
+/// use gwasm_api::redundancy::Redundancy;
+///
+/// // accept results which at least 2 out of 3 replicas agree on, ignoring
+/// // trailing whitespace
+/// let trimmed = |bytes: &[u8]| String::from_utf8_lossy(bytes).trim_end().to_owned();
+/// let redundancy = Redundancy::new(3)
+///     .comparator(move |a, b| trimmed(a) == trimmed(b))
+///     .majority(true);
+/// assert_eq!(redundancy.replicas(), 3);
+/// ```
+#[derive(Clone)]
+pub struct Redundancy {
+    replicas: usize,
+    comparator: Option<Comparator>,
+    majority: bool,
+}
+
+impl Redundancy {
+    /// Creates a new `Redundancy` instance computing each subtask `replicas`
+    /// times
+    ///
+    /// `replicas` of zero is treated as one, i.e., no redundancy.
+    pub fn new(replicas: usize) -> Self {
+        Self {
+            replicas: replicas.max(1),
+            comparator: None,
+            majority: false,
+        }
+    }
+
+    /// Sets the comparator used to decide whether the contents of two output
+    /// files of the replicas are equivalent, instead of comparing them byte by byte
+    pub fn comparator<F>(mut self, comparator: F) -> Self
+    where
+        F: Fn(&[u8], &[u8]) -> bool + Send + Sync + 'static,
+    {
+        self.comparator = Some(Arc::new(comparator));
+        self
+    }
+
+    /// Sets whether the output agreed on by the majority of replicas is
+    /// accepted, instead of requiring all replicas to agree
+    pub fn majority(mut self, majority: bool) -> Self {
+        self.majority = majority;
+        self
+    }
+
+    /// Number of times each subtask is computed
+    pub fn replicas(&self) -> usize {
+        self.replicas
+    }
+
+    /// Returns `true` if the output agreed on by the majority of replicas
+    /// is accepted
+    pub(crate) fn is_majority(&self) -> bool {
+        self.majority
+    }
+
+    /// Compares the outputs of the replicas of `subtask`, each given as a map
+    /// from the subtask's output file paths to the actual files, along with
+    /// the provider nodes which computed them, if known
+    ///
+    /// Returns the index of the replica whose output is accepted, or the
+    /// [`Disagreement`] between the replicas. Replicas with missing output
+    /// files don't agree with any other replica, and neither do replicas
+    /// computed by the same node.
+    pub(crate) fn verify(
+        &self,
+        subtask: &str,
+        outputs: &[BTreeMap<PathBuf, PathBuf>],
+        nodes: &[Option<&str>],
+    ) -> Result<std::result::Result<usize, Disagreement>> {
+        // partition the replicas with complete outputs into groups of
+        // equivalent outputs, each represented by its first replica
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for (i, output) in outputs.iter().enumerate() {
+            if !output.values().all(|path| path.is_file()) {
+                continue;
+            }
+            let mut group = None;
+            for (g, members) in groups.iter().enumerate() {
+                let same_node = nodes[i].is_some() && members.iter().any(|&m| nodes[m] == nodes[i]);
+                if !same_node && self.equivalent(&outputs[members[0]], output)? {
+                    group = Some(g);
+                    break;
+                }
+            }
+            match group {
+                Some(g) => groups[g].push(i),
+                None => groups.push(vec![i]),
+            }
+        }
+
+        let largest = groups.iter().max_by_key(|members| members.len());
+        let agreeing = largest.map_or(0, Vec::len);
+        let accepted = if self.majority {
+            agreeing * 2 > outputs.len()
+        } else {
+            agreeing == outputs.len()
+        };
+        match largest {
+            Some(members) if accepted => Ok(Ok(members[0])),
+            _ => Ok(Err(Disagreement {
+                subtask: subtask.to_owned(),
+                replicas: outputs.len(),
+                agreeing,
+            })),
+        }
+    }
+
+    fn equivalent(
+        &self,
+        lhs: &BTreeMap<PathBuf, PathBuf>,
+        rhs: &BTreeMap<PathBuf, PathBuf>,
+    ) -> Result<bool> {
+        if !lhs.keys().eq(rhs.keys()) {
+            return Ok(false);
+        }
+        for (lhs, rhs) in lhs.values().zip(rhs.values()) {
+            if !self.equivalent_files(lhs, rhs)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn equivalent_files(&self, lhs: &Path, rhs: &Path) -> Result<bool> {
+        match &self.comparator {
+            Some(comparator) => {
+                let lhs = fs::read(lhs).file_context(lhs)?;
+                let rhs = fs::read(rhs).file_context(rhs)?;
+                Ok(comparator(&lhs, &rhs))
+            }
+            None => Ok(Digest::of_file(lhs)? == Digest::of_file(rhs)?),
+        }
+    }
+}
+
+impl fmt::Debug for Redundancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redundancy")
+            .field("replicas", &self.replicas)
+            .field("comparator", &self.comparator.as_ref().map(|_| "<fn>"))
+            .field("majority", &self.majority)
+            .finish()
+    }
+}
+
+/// Verification failure of a subtask whose replicas did not agree on
+/// the output
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disagreement {
+    /// Subtask's name
+    pub subtask: String,
+    /// Number of replicas of the subtask
+    pub replicas: usize,
+    /// Size of the largest group of replicas which agreed on the output
+    pub agreeing: usize,
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} of {} replicas agree)",
+            self.subtask, self.agreeing, self.replicas
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn outputs(dir: &Path, contents: &[Option<&str>]) -> Vec<BTreeMap<PathBuf, PathBuf>> {
+        contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let path = dir.join(format!("out{}", i));
+                if let Some(content) = content {
+                    fs::write(&path, content).unwrap();
+                }
+                vec![(PathBuf::from("out"), path)].into_iter().collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unanimous() {
+        let dir = tempdir().unwrap();
+        let redundancy = Redundancy::new(3);
+        let agree = outputs(dir.path(), &[Some("a"), Some("a"), Some("a")]);
+        assert_eq!(redundancy.verify("s", &agree, &[None; 3]).unwrap(), Ok(0));
+
+        let disagree = outputs(dir.path(), &[Some("a"), Some("b"), Some("a")]);
+        let disagreement = redundancy
+            .verify("s", &disagree, &[None; 3])
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(disagreement.agreeing, 2);
+    }
+
+    #[test]
+    fn majority() {
+        let dir = tempdir().unwrap();
+        let redundancy = Redundancy::new(3).majority(true);
+        let contents = outputs(dir.path(), &[None, Some("b"), Some("b")]);
+        assert_eq!(
+            redundancy.verify("s", &contents, &[None; 3]).unwrap(),
+            Ok(1)
+        );
+
+        let contents = outputs(dir.path(), &[None, Some("a"), Some("b")]);
+        assert!(redundancy
+            .verify("s", &contents, &[None; 3])
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn same_node() {
+        let dir = tempdir().unwrap();
+        let redundancy = Redundancy::new(3).majority(true);
+        let contents = outputs(dir.path(), &[Some("a"), Some("a"), Some("b")]);
+        let nodes = [Some("x"), Some("y"), Some("z")];
+        assert_eq!(redundancy.verify("s", &contents, &nodes).unwrap(), Ok(0));
+
+        let nodes = [Some("x"), Some("x"), Some("z")];
+        let disagreement = redundancy
+            .verify("s", &contents, &nodes)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(disagreement.agreeing, 1);
+    }
+
+    #[test]
+    fn custom_comparator() {
+        let dir = tempdir().unwrap();
+        let redundancy = Redundancy::new(2).comparator(|a, b| a.eq_ignore_ascii_case(b));
+        let contents = outputs(dir.path(), &[Some("abc"), Some("ABC")]);
+        assert_eq!(
+            redundancy.verify("s", &contents, &[None; 2]).unwrap(),
+            Ok(0)
+        );
+    }
+}
@@ -0,0 +1,436 @@
+//! Declarative specification of gWasm tasks
+//!
+//! Instead of configuring a [`TaskBuilder`] in code, a task can be described
+//! in a [`TaskSpec`] file kept under version control, in one of the formats:
+//! * JSON, always available
+//! * TOML, available with the `toml` feature
+//! * YAML, available with the `yaml` feature
+//!
+//! Relative paths in a spec loaded with [`TaskSpec::from_file`] are relative
+//! to the dir containing the spec file. A spec which cannot be parsed, or has
+//! an invalid value, is reported as [`Error::InvalidSpec`] pointing at the
+//! offending field, such as `subtasks[1].input`.
+//!
+//! # Example:
+//! ```
+//! use gwasm_api::spec::TaskSpec;
+//! use std::fs;
+//! use tempfile::tempdir;
+//!
+//! let dir = tempdir().unwrap();
+//! fs::write(dir.path().join("app.js"), b"").unwrap();
+//! fs::write(dir.path().join("app.wasm"), b"").unwrap();
+//! fs::write(dir.path().join("in.txt"), b"input").unwrap();
+//! let spec_path = dir.path().join("task.json");
+//! fs::write(&spec_path, r#"{
+//!     "name": "app",
+//!     "bid": 0.5,
+//!     "subtask_timeout": "10m",
+//!     "binary": { "prefix": "app" },
+//!     "subtasks": [{ "input": "in.txt", "args": ["--fast"] }]
+//! }"#).unwrap();
+//!
+//! let workspace = tempdir().unwrap();
+//! let task = TaskSpec::from_file(&spec_path)
+//!     .unwrap()
+//!     .into_builder(&workspace)
+//!     .unwrap()
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(task.name(), "app");
+//! ```
+//!
+//! [`TaskBuilder`]: ../task/struct.TaskBuilder.html
+//! [`TaskSpec`]: struct.TaskSpec.html
+//! [`TaskSpec::from_file`]: struct.TaskSpec.html#method.from_file
+//! [`Error::InvalidSpec`]: ../error/enum.Error.html#variant.InvalidSpec
+use super::{
+    error::{Error, FileContext},
+    input::FileMode,
+    task::{GWasmBinary, TaskBuilder},
+    timeout::Timeout,
+    Result,
+};
+use serde::{Deserialize, Deserializer};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Specification of a gWasm task, which can be loaded into a [`TaskBuilder`]
+///
+/// [`TaskBuilder`]: ../task/struct.TaskBuilder.html
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskSpec {
+    /// Paths to the task's gWasm binary
+    pub binary: BinarySpec,
+    /// Task's name
+    pub name: Option<String>,
+    /// Task's bid value
+    pub bid: Option<f64>,
+    /// Task's budget
+    pub budget: Option<f64>,
+    /// Task's [`Timeout`](../timeout/struct.Timeout.html) value
+    pub timeout: Option<Timeout>,
+    /// Subtasks' [`Timeout`](../timeout/struct.Timeout.html) value
+    pub subtask_timeout: Option<Timeout>,
+    /// Path to the dir where the final output of the task is expected
+    pub output_path: Option<PathBuf>,
+    /// Task's [metadata](../task/struct.TaskBuilder.html#method.metadata)
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Task's subtasks, in order
+    pub subtasks: Vec<SubtaskSpec>,
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+/// Paths to the files making up a gWasm binary, given either as the path
+/// prefix of Emscripten build output, or as paths to both files
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BinarySpec {
+    /// Path prefix of Emscripten build output, e.g. `build/app` for
+    /// `build/app.js` and `build/app.wasm`
+    pub prefix: Option<PathBuf>,
+    /// Path to the JavaScript file
+    pub js: Option<PathBuf>,
+    /// Path to the Wasm binary
+    pub wasm: Option<PathBuf>,
+}
+
+/// Specification of a single subtask
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubtaskSpec {
+    /// Path to the subtask's input file
+    pub input: PathBuf,
+    /// Subtask's [name](../task/struct.TaskBuilder.html#method.subtask_name),
+    /// `subtask_{i}` by default
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Additional CLI arguments passed to the Wasm binary
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Subtask's [tags](../task/struct.TaskBuilder.html#method.subtask_tag)
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl TaskSpec {
+    /// Parses a `TaskSpec` from JSON
+    pub fn from_json_str(spec: &str) -> Result<Self> {
+        parse(&mut serde_json::Deserializer::from_str(spec))
+    }
+
+    /// Parses a `TaskSpec` from TOML
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(spec: &str) -> Result<Self> {
+        parse(&mut toml::Deserializer::new(spec))
+    }
+
+    /// Parses a `TaskSpec` from YAML
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(spec: &str) -> Result<Self> {
+        parse(serde_yaml::Deserializer::from_str(spec))
+    }
+
+    /// Reads a `TaskSpec` from the file at `path`, in the format given by
+    /// its extension: `json`, `toml`, or `yaml` or `yml`
+    ///
+    /// Relative paths in the spec are resolved against the dir containing
+    /// the file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        let parse = match extension {
+            Some("json") => Self::from_json_str as fn(&str) -> Result<Self>,
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml_str,
+            #[cfg(feature = "yaml")]
+            Some("yaml") | Some("yml") => Self::from_yaml_str,
+            _ => {
+                return Err(Error::InvalidSpec {
+                    field: None,
+                    reason: format!("unsupported spec format of {}", path.display()),
+                })
+            }
+        };
+        let mut spec = parse(&fs::read_to_string(path).file_context(path)?)?;
+        spec.base_dir = path.parent().map(Path::to_owned).unwrap_or_default();
+        Ok(spec)
+    }
+
+    /// Checks the spec's values, failing with [`Error::InvalidSpec`] on the
+    /// first offending field
+    ///
+    /// [`Error::InvalidSpec`]: ../error/enum.Error.html#variant.InvalidSpec
+    pub fn validate(&self) -> Result<()> {
+        self.binary_paths()?;
+        if let Some(name) = &self.name {
+            if name.is_empty() {
+                return Err(invalid_field("name", "must not be empty"));
+            }
+        }
+        if let Some(bid) = self.bid {
+            if !(bid.is_finite() && bid > 0.0) {
+                return Err(invalid_field("bid", "must be a positive number"));
+            }
+        }
+        if let Some(budget) = self.budget {
+            if !(budget.is_finite() && budget >= 0.0) {
+                return Err(invalid_field("budget", "must be a non-negative number"));
+            }
+        }
+        if self.subtasks.is_empty() {
+            return Err(invalid_field("subtasks", "no subtasks given"));
+        }
+        for (i, subtask) in self.subtasks.iter().enumerate() {
+            let field = format!("subtasks[{}].input", i);
+            self.existing_file(&field, &subtask.input)?;
+        }
+        Ok(())
+    }
+
+    /// Validates the spec, and creates a [`TaskBuilder`] in `workspace`
+    /// configured according to it
+    ///
+    /// The input files of the subtasks are copied into the workspace once
+    /// the task is built.
+    ///
+    /// [`TaskBuilder`]: ../task/struct.TaskBuilder.html
+    pub fn into_builder<P: AsRef<Path>>(self, workspace: P) -> Result<TaskBuilder<'static>> {
+        self.validate()?;
+        let (js, wasm) = self.binary_paths()?;
+        let mut builder = TaskBuilder::try_new(workspace, GWasmBinary::from_files(js, wasm)?)?;
+        if let Some(name) = &self.name {
+            builder = builder.name(name);
+        }
+        if let Some(bid) = self.bid {
+            builder = builder.bid(bid);
+        }
+        if let Some(budget) = self.budget {
+            builder = builder.budget(budget);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(subtask_timeout) = self.subtask_timeout {
+            builder = builder.subtask_timeout(subtask_timeout);
+        }
+        if let Some(output_path) = &self.output_path {
+            builder = builder.output_path(self.base_dir.join(output_path));
+        }
+        for (key, value) in self.metadata {
+            builder = builder.metadata(key, value);
+        }
+        for subtask in self.subtasks {
+            builder = builder
+                .push_subtask_file(self.base_dir.join(&subtask.input), FileMode::Copy)
+                .subtask_args(subtask.args);
+            if let Some(name) = subtask.name {
+                builder = builder.subtask_name(name);
+            }
+            for (key, value) in subtask.tags {
+                builder = builder.subtask_tag(key, value);
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Resolves the paths to the JavaScript file and the Wasm binary
+    fn binary_paths(&self) -> Result<(PathBuf, PathBuf)> {
+        let BinarySpec { prefix, js, wasm } = &self.binary;
+        let (js, wasm) = match (prefix, js, wasm) {
+            (Some(prefix), None, None) => {
+                let prefix = prefix.as_os_str();
+                let (mut js, mut wasm) = (prefix.to_owned(), prefix.to_owned());
+                js.push(".js");
+                wasm.push(".wasm");
+                self.existing_file("binary.prefix", Path::new(&js))?;
+                self.existing_file("binary.prefix", Path::new(&wasm))?;
+                (PathBuf::from(js), PathBuf::from(wasm))
+            }
+            (Some(_), _, _) => {
+                return Err(invalid_field(
+                    "binary",
+                    "either prefix, or js and wasm must be given, not both",
+                ))
+            }
+            (None, Some(js), Some(wasm)) => {
+                self.existing_file("binary.js", js)?;
+                self.existing_file("binary.wasm", wasm)?;
+                (js.clone(), wasm.clone())
+            }
+            (None, None, _) => return Err(invalid_field("binary.js", "missing")),
+            (None, _, None) => return Err(invalid_field("binary.wasm", "missing")),
+        };
+        Ok((self.base_dir.join(js), self.base_dir.join(wasm)))
+    }
+
+    fn existing_file(&self, field: &str, path: &Path) -> Result<()> {
+        let path = self.base_dir.join(path);
+        if path.is_file() {
+            Ok(())
+        } else {
+            Err(invalid_field(
+                field,
+                &format!("no such file {}", path.display()),
+            ))
+        }
+    }
+}
+
+fn invalid_field(field: &str, reason: &str) -> Error {
+    Error::InvalidSpec {
+        field: Some(field.to_owned()),
+        reason: reason.to_owned(),
+    }
+}
+
+// deserializes the spec, tracking the path to the field which failed to
+// deserialize
+fn parse<'de, D>(deserializer: D) -> Result<TaskSpec>
+where
+    D: Deserializer<'de>,
+    D::Error: fmt::Display,
+{
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let field = err.path().to_string();
+        let reason = err.into_inner().to_string();
+        // some parsers already prefix their errors with the field
+        let prefix = format!("{}: ", field);
+        let reason = reason.strip_prefix(&prefix).unwrap_or(&reason).to_owned();
+        Error::InvalidSpec {
+            field: Some(field).filter(|field| field != "."),
+            reason,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+
+    fn spec_dir() -> TempDir {
+        let dir = tempdir().unwrap();
+        for name in &["app.js", "app.wasm", "in0", "in1"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+        dir
+    }
+
+    fn field(err: Error) -> Option<String> {
+        match err {
+            Error::InvalidSpec { field, .. } => field,
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn json_spec() {
+        let dir = spec_dir();
+        let path = dir.path().join("spec.json");
+        let spec = r#"{
+            "name": "spec",
+            "binary": { "js": "app.js", "wasm": "app.wasm" },
+            "metadata": { "job_id": "1234" },
+            "subtasks": [
+                { "input": "in0", "args": ["-v"], "tags": { "part": "first" } },
+                { "input": "in1", "name": "second" }
+            ]
+        }"#;
+        fs::write(&path, spec).unwrap();
+
+        let workspace = tempdir().unwrap();
+        let task = TaskSpec::from_file(&path)
+            .unwrap()
+            .into_builder(&workspace)
+            .unwrap()
+            .build()
+            .unwrap();
+        let (names, args): (Vec<&str>, Vec<Vec<&str>>) = task
+            .options()
+            .subtasks()
+            .map(|(name, subtask)| (name, subtask.exec_args().collect()))
+            .unzip();
+        assert_eq!(names, vec!["subtask_0", "second"]);
+        assert_eq!(args, vec![vec!["in0", "out", "-v"], vec!["in1", "out"]]);
+        assert_eq!(task.metadata()["job_id"], "1234");
+        assert_eq!(task.subtask_tags("subtask_0").unwrap()["part"], "first");
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let dir = spec_dir();
+        let spec = |spec: &str| {
+            let path = dir.path().join("spec.json");
+            fs::write(&path, spec).unwrap();
+            TaskSpec::from_file(&path).unwrap().validate().unwrap_err()
+        };
+
+        let err = spec(
+            r#"{ "binary": { "prefix": "app" }, "subtasks": [
+            { "input": "in0" }, { "input": "missing" }
+        ] }"#,
+        );
+        assert_eq!(field(err), Some("subtasks[1].input".into()));
+
+        let err = spec(r#"{ "binary": { "js": "app.js" }, "subtasks": [] }"#);
+        assert_eq!(field(err), Some("binary.wasm".into()));
+
+        let err = spec(r#"{ "bid": -1, "binary": { "prefix": "app" }, "subtasks": [] }"#);
+        assert_eq!(field(err), Some("bid".into()));
+
+        let err = TaskSpec::from_json_str(r#"{ "bidd": 1 }"#).unwrap_err();
+        assert!(err.to_string().contains("bidd"));
+
+        let err = TaskSpec::from_json_str(r#"{ "subtask_timeout": "10x" }"#).unwrap_err();
+        assert_eq!(field(err), Some("subtask_timeout".into()));
+
+        let err = TaskSpec::from_json_str(r#"{ "subtasks": [{ "input": "in0", "args": [1] }] }"#)
+            .unwrap_err();
+        assert_eq!(field(err), Some("subtasks[0].args[0]".into()));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_spec() {
+        let spec = TaskSpec::from_toml_str(
+            r#"
+            name = "spec"
+            subtask_timeout = "1h30m"
+
+            [binary]
+            prefix = "app"
+
+            [[subtasks]]
+            input = "in0"
+            args = ["-v"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(spec.subtask_timeout.unwrap().as_secs(), 5400);
+        assert_eq!(spec.subtasks[0].args, vec!["-v"]);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_spec() {
+        let spec = TaskSpec::from_yaml_str(
+            "
+            name: spec
+            binary:
+              prefix: app
+            subtasks:
+              - input: in0
+                args: [-v]
+            ",
+        )
+        .unwrap();
+        assert_eq!(spec.subtasks[0].input, Path::new("in0"));
+    }
+}
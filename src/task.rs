@@ -1,26 +1,178 @@
 //! Convenience types for creating and managing gWasm tasks
 use super::{
+    codec::Codec,
+    compat::{self, CompatPolicy, Incompatibility},
+    digest::{self, Digest},
     error::{Error, FileContext},
+    golem::SubtaskFailure,
+    input::{self, Deduplicated, FileMode, SubtaskInput},
+    redundancy::Redundancy,
     timeout::Timeout,
-    Result,
+    validation,
+    workspace::{self, Sidecar, Staging, TempWorkspace, WorkspacePolicy},
+    ProgressUpdate, Result,
+};
+use futures::{executor::block_on_stream, Stream};
+use serde::{
+    de::{DeserializeOwned, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
 };
-use serde::Serialize;
 use std::{
-    collections::BTreeMap,
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
     convert::TryFrom,
+    fmt,
     fs::{self, File},
-    io::BufReader,
+    io::{self, BufReader, BufWriter, Read, Write},
+    iter,
     path::{Component, Path, PathBuf},
     str::FromStr,
+    sync::Arc,
 };
 
+/// Source of the contents of a file making up a gWasm binary
+#[derive(Debug, Clone)]
+pub enum BinarySource<'a> {
+    /// Borrowed contents of the file
+    Borrowed(&'a [u8]),
+    /// Owned contents of the file
+    Owned(Vec<u8>),
+    /// Path to the file, which is only read when needed
+    File(PathBuf),
+}
+
+impl<'a> BinarySource<'a> {
+    /// Returns the contents of the file, reading it from disk if necessary
+    pub fn read(&self) -> Result<Cow<'_, [u8]>> {
+        match self {
+            Self::Borrowed(contents) => Ok(Cow::Borrowed(contents)),
+            Self::Owned(contents) => Ok(Cow::Borrowed(contents)),
+            Self::File(path) => fs::read(path).map(Cow::Owned).file_context(path),
+        }
+    }
+
+    fn write_to(&self, path: &Path) -> Result<()> {
+        match self {
+            Self::Borrowed(contents) => fs::write(path, contents).file_context(path),
+            Self::Owned(contents) => fs::write(path, contents).file_context(path),
+            Self::File(source) => fs::copy(source, path).map(|_| ()).file_context(source),
+        }
+    }
+}
+
+impl<'a> From<&'a [u8]> for BinarySource<'a> {
+    fn from(contents: &'a [u8]) -> Self {
+        Self::Borrowed(contents)
+    }
+}
+
+impl<'a> From<&'a Vec<u8>> for BinarySource<'a> {
+    fn from(contents: &'a Vec<u8>) -> Self {
+        Self::Borrowed(contents)
+    }
+}
+
+impl From<Vec<u8>> for BinarySource<'static> {
+    fn from(contents: Vec<u8>) -> Self {
+        Self::Owned(contents)
+    }
+}
+
+impl From<PathBuf> for BinarySource<'static> {
+    fn from(path: PathBuf) -> Self {
+        Self::File(path)
+    }
+}
+
 /// Wrapper type for easy passing of gWasm binary
-#[derive(Debug)]
+///
+/// The JavaScript and Wasm files can be passed either as borrowed or owned
+/// buffers, or as paths to the files, in which case they are copied straight
+/// into the workspace when the [`Task`] is built.
+///
+/// # Example:
+/// ```
+/// use gwasm_api::task::GWasmBinary;
+/// use std::fs;
+/// use tempfile::tempdir;
+///
+/// let build_dir = tempdir().unwrap();
+/// fs::write(build_dir.path().join("app.js"), b"").unwrap();
+/// fs::write(build_dir.path().join("app.wasm"), b"").unwrap();
+///
+/// let binary = GWasmBinary::from_prefix(build_dir.path().join("app"));
+/// assert!(binary.is_ok());
+/// ```
+///
+/// [`Task`]: ../task/struct.Task.html
+#[derive(Debug, Clone)]
 pub struct GWasmBinary<'a> {
     /// Contents of JavaScript file generated by Emscripten
-    pub js: &'a [u8],
+    pub js: BinarySource<'a>,
     /// Contents of Wasm file generated by Emscripten
-    pub wasm: &'a [u8],
+    pub wasm: BinarySource<'a>,
+}
+
+impl<'a> GWasmBinary<'a> {
+    /// Creates new `GWasmBinary` from the contents of JavaScript and Wasm files
+    pub fn new<J, W>(js: J, wasm: W) -> Self
+    where
+        J: Into<BinarySource<'a>>,
+        W: Into<BinarySource<'a>>,
+    {
+        Self {
+            js: js.into(),
+            wasm: wasm.into(),
+        }
+    }
+
+    /// Validates the binary for a task called `name`
+    ///
+    /// See [`validation`] for details.
+    ///
+    /// [`validation`]: ../validation/index.html
+    pub fn validate(&self, name: &str) -> Result<()> {
+        validation::validate(self, name)
+    }
+}
+
+impl GWasmBinary<'static> {
+    /// Creates new `GWasmBinary` from paths to JavaScript and Wasm files
+    ///
+    /// Returns an error if either of the paths does not exist or is not a file.
+    pub fn from_files<J, W>(js: J, wasm: W) -> Result<Self>
+    where
+        J: AsRef<Path>,
+        W: AsRef<Path>,
+    {
+        let js = existing_file(js.as_ref())?;
+        let wasm = existing_file(wasm.as_ref())?;
+        Ok(Self::new(js, wasm))
+    }
+
+    /// Creates new `GWasmBinary` from the path prefix of Emscripten build output
+    ///
+    /// For example, prefix `build/app` corresponds to files `build/app.js`
+    /// and `build/app.wasm`.
+    pub fn from_prefix<P: AsRef<Path>>(prefix: P) -> Result<Self> {
+        let prefix = prefix.as_ref().as_os_str();
+        let mut js = prefix.to_owned();
+        js.push(".js");
+        let mut wasm = prefix.to_owned();
+        wasm.push(".wasm");
+        Self::from_files(js, wasm)
+    }
+}
+
+fn existing_file(path: &Path) -> Result<PathBuf> {
+    if fs::metadata(path).file_context(path)?.is_file() {
+        Ok(path.to_owned())
+    } else {
+        Err(Error::FileError(
+            io::Error::new(io::ErrorKind::InvalidInput, "not a file"),
+            path.to_owned(),
+        ))
+    }
 }
 
 /// gWasm task builder
@@ -36,10 +188,7 @@ pub struct GWasmBinary<'a> {
 /// use std::path::Path;
 /// use tempfile::tempdir;
 ///
-/// let binary = GWasmBinary {
-///     js: &[],
-///     wasm: &[],
-/// };
+/// let binary = GWasmBinary::new(Vec::new(), Vec::new());
 /// let workspace = tempdir().unwrap();
 /// let task = TaskBuilder::try_new(&workspace, binary).unwrap().build();
 /// assert!(task.is_ok());
@@ -56,12 +205,22 @@ pub struct TaskBuilder<'a> {
     name: Option<String>,
     bid: Option<f64>,
     budget: Option<f64>,
+    budget_cap: Option<f64>,
+    validate_binary: bool,
+    compat_policy: CompatPolicy,
     timeout: Option<Timeout>,
     subtask_timeout: Option<Timeout>,
-    input_dir_path: PathBuf,
-    output_dir_path: PathBuf,
+    workspace: PathBuf,
+    workspace_policy: WorkspacePolicy,
     output_path: Option<PathBuf>,
-    subtask_data: Vec<Vec<u8>>,
+    subtasks: Vec<PendingSubtask>,
+    shared_inputs: Vec<(String, SubtaskInput)>,
+    parallelism: usize,
+    deduplicate_inputs: bool,
+    verify_inputs: bool,
+    redundancy: Option<Redundancy>,
+    partial_results: bool,
+    metadata: BTreeMap<String, String>,
 }
 
 impl<'a> TaskBuilder<'a> {
@@ -76,12 +235,22 @@ impl<'a> TaskBuilder<'a> {
             name: None,
             bid: None,
             budget: None,
+            budget_cap: None,
+            validate_binary: false,
+            compat_policy: CompatPolicy::default(),
             timeout: None,
             subtask_timeout: None,
-            input_dir_path: workspace.as_ref().join("in"),
-            output_dir_path: workspace.as_ref().join("out"),
+            workspace: workspace.as_ref().into(),
+            workspace_policy: WorkspacePolicy::default(),
             output_path: None,
-            subtask_data: Vec::new(),
+            subtasks: Vec::new(),
+            shared_inputs: Vec::new(),
+            parallelism: 1,
+            deduplicate_inputs: false,
+            verify_inputs: false,
+            redundancy: None,
+            partial_results: false,
+            metadata: BTreeMap::new(),
         }
     }
 
@@ -92,6 +261,26 @@ impl<'a> TaskBuilder<'a> {
         Ok(Self::new(abspath, binary))
     }
 
+    /// Creates new `TaskBuilder` with a temporary workspace and `GWasmBinary`
+    ///
+    /// The workspace is created in the system's temporary dir when the task
+    /// is built, and follows the [`WorkspacePolicy::Temp`] policy.
+    ///
+    /// [`WorkspacePolicy::Temp`]: ../workspace/enum.WorkspacePolicy.html#variant.Temp
+    pub fn try_new_temp(binary: GWasmBinary<'a>) -> Result<Self> {
+        Ok(Self::try_new(std::env::temp_dir(), binary)?.workspace_policy(WorkspacePolicy::Temp))
+    }
+
+    /// Sets how the workspace is treated when building the task
+    ///
+    /// See [`WorkspacePolicy`] for details.
+    ///
+    /// [`WorkspacePolicy`]: ../workspace/enum.WorkspacePolicy.html
+    pub fn workspace_policy(mut self, workspace_policy: WorkspacePolicy) -> Self {
+        self.workspace_policy = workspace_policy;
+        self
+    }
+
     /// Sets task's name
     pub fn name<S: AsRef<str>>(mut self, name: S) -> Self {
         self.name = Some(name.as_ref().to_owned());
@@ -105,11 +294,55 @@ impl<'a> TaskBuilder<'a> {
     }
 
     /// Sets task's budget value
+    ///
+    /// If the [estimated cost] of the task exceeds the budget, [`build`] will
+    /// refuse to create the task with [`Error::BudgetExceeded`].
+    ///
+    /// [estimated cost]: struct.Task.html#method.estimated_cost
+    /// [`build`]: struct.TaskBuilder.html#method.build
+    /// [`Error::BudgetExceeded`]: ../error/enum.Error.html#variant.BudgetExceeded
     pub fn budget(mut self, budget: f64) -> Self {
         self.budget = Some(budget);
         self
     }
 
+    /// Sets task's hard spending cap
+    ///
+    /// While the task is computed, the amount spent is tracked, and once it
+    /// passes the cap, the task is aborted with [`Error::BudgetCapExceeded`].
+    /// If Golem doesn't report the amount spent, the cap can't be enforced,
+    /// and the task is aborted with [`Error::EmptyCost`] instead.
+    ///
+    /// [`Error::BudgetCapExceeded`]: ../error/enum.Error.html#variant.BudgetCapExceeded
+    /// [`Error::EmptyCost`]: ../error/enum.Error.html#variant.EmptyCost
+    pub fn budget_cap(mut self, budget_cap: f64) -> Self {
+        self.budget_cap = Some(budget_cap);
+        self
+    }
+
+    /// Sets whether the [`GWasmBinary`] should be validated when building the task
+    ///
+    /// Validation is disabled by default. See [`validation`] for details.
+    ///
+    /// [`GWasmBinary`]: struct.GWasmBinary.html
+    /// [`validation`]: ../validation/index.html
+    pub fn validate_binary(mut self, validate_binary: bool) -> Self {
+        self.validate_binary = validate_binary;
+        self
+    }
+
+    /// Sets how a [`GWasmBinary`] incompatible with the sp-wasm sandbox is treated
+    /// when building the task
+    ///
+    /// By default, the binary is not analyzed. See [`compat`] for details.
+    ///
+    /// [`GWasmBinary`]: struct.GWasmBinary.html
+    /// [`compat`]: ../compat/index.html
+    pub fn compat_policy(mut self, compat_policy: CompatPolicy) -> Self {
+        self.compat_policy = compat_policy;
+        self
+    }
+
     /// Sets task's [`Timeout`](../timeout/struct.Timeout.html) value
     pub fn timeout(mut self, timeout: Timeout) -> Self {
         self.timeout = Some(timeout);
@@ -137,20 +370,350 @@ impl<'a> TaskBuilder<'a> {
     /// [general crate docs].
     ///
     /// [general crate docs]: ../index.html
-    pub fn push_subtask_data<T: Into<Vec<u8>>>(mut self, data: T) -> Self {
-        self.subtask_data.push(data.into());
+    pub fn push_subtask_data<T: Into<Vec<u8>>>(self, data: T) -> Self {
+        self.push_subtask_input(SubtaskInput::Bytes(data.into()))
+    }
+
+    /// Pushes subtask data from any [`SubtaskInput`] source into the buffer
+    ///
+    /// Unlike with [`push_subtask_data`], the data is not loaded into memory
+    /// until [`build`] is executed, and is then written to the workspace
+    /// with bounded memory.
+    ///
+    /// [`SubtaskInput`]: ../input/enum.SubtaskInput.html
+    /// [`push_subtask_data`]: struct.TaskBuilder.html#method.push_subtask_data
+    /// [`build`]: struct.TaskBuilder.html#method.build
+    pub fn push_subtask_input<T: Into<SubtaskInput>>(mut self, input: T) -> Self {
+        self.subtasks.push(PendingSubtask {
+            input: input.into(),
+            name: None,
+            args: Vec::new(),
+            tags: BTreeMap::new(),
+        });
+        self
+    }
+
+    /// Names the most recently pushed subtask, instead of the default
+    /// `subtask_{i}`, where `i` is its index
+    ///
+    /// The name is used for the subtask's input and output dirs, and is
+    /// returned as [`ComputedSubtask::name`]. It has to be a plain file name,
+    /// unique among the task's subtasks (and their replicas, named
+    /// `{name}_replica_{j}`), or else [`build`] fails with
+    /// [`Error::InvalidSubtaskName`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no subtask has been pushed yet.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use gwasm_api::prelude::*;
+    ///
+    /// let binary = GWasmBinary::new(Vec::new(), Vec::new());
+    /// let workspace = tempfile::tempdir().unwrap();
+    /// let task = TaskBuilder::try_new(&workspace, binary)
+    ///     .unwrap()
+    ///     .push_subtask_data(vec![0u8; 16])
+    ///     .subtask_name("first_frame")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(task.options().subtasks().next().unwrap().0, "first_frame");
+    /// ```
+    ///
+    /// [`ComputedSubtask::name`]: struct.ComputedSubtask.html#structfield.name
+    /// [`build`]: struct.TaskBuilder.html#method.build
+    /// [`Error::InvalidSubtaskName`]: ../error/enum.Error.html#variant.InvalidSubtaskName
+    pub fn subtask_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.subtasks
+            .last_mut()
+            .expect("subtask_name called before pushing any subtask")
+            .name = Some(name.into());
+        self
+    }
+
+    /// Appends `args` to the CLI arguments of the most recently pushed subtask
+    ///
+    /// The arguments are passed to the Wasm binary after the input and output
+    /// file paths (see [general crate docs]), and can be used e.g. to refer to
+    /// a [shared input].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no subtask has been pushed yet.
+    ///
+    /// [general crate docs]: ../index.html
+    /// [shared input]: struct.TaskBuilder.html#method.push_shared_input
+    pub fn subtask_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.subtasks
+            .last_mut()
+            .expect("subtask_args called before pushing any subtask")
+            .args
+            .extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Tags the most recently pushed subtask with `key` set to `value`
+    ///
+    /// Tags are not sent to Golem. They are stored in the workspace, and
+    /// returned with the subtask's [`ComputedSubtask`], which makes it
+    /// possible to correlate the results with e.g. the jobs they belong to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no subtask has been pushed yet.
+    ///
+    /// [`ComputedSubtask`]: struct.ComputedSubtask.html#structfield.tags
+    pub fn subtask_tag<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.subtasks
+            .last_mut()
+            .expect("subtask_tag called before pushing any subtask")
+            .tags
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Pushes an input shared by all subtasks into the buffer
+    ///
+    /// Unlike subtask data, the shared input is written only once, to a file
+    /// called `name` at the root of the task's input dir, alongside the
+    /// JavaScript and Wasm files. Subtasks can be told about it through
+    /// their [args].
+    ///
+    /// `name` must be a plain file name which does not collide with the names
+    /// of the JavaScript and Wasm files, nor with the dirs of the subtasks and
+    /// their replicas, or else [`build`] will fail with
+    /// [`Error::InvalidSharedInputName`].
+    ///
+    /// [args]: struct.TaskBuilder.html#method.subtask_args
+    /// [`build`]: struct.TaskBuilder.html#method.build
+    /// [`Error::InvalidSharedInputName`]: ../error/enum.Error.html#variant.InvalidSharedInputName
+    pub fn push_shared_input<S, T>(mut self, name: S, input: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<SubtaskInput>,
+    {
+        self.shared_inputs.push((name.into(), input.into()));
+        self
+    }
+
+    /// Pushes subtask data read until EOF from `reader` into the buffer
+    ///
+    /// See [`push_subtask_input`] for details.
+    ///
+    /// [`push_subtask_input`]: struct.TaskBuilder.html#method.push_subtask_input
+    pub fn push_subtask_reader<R: Read + Send + 'static>(self, reader: R) -> Self {
+        self.push_subtask_input(SubtaskInput::Reader(Box::new(reader)))
+    }
+
+    /// Pushes subtask data stored in file at `path` into the buffer
+    ///
+    /// The file is placed in the workspace according to the [`FileMode`].
+    /// See [`push_subtask_input`] for details.
+    ///
+    /// [`FileMode`]: ../input/enum.FileMode.html
+    /// [`push_subtask_input`]: struct.TaskBuilder.html#method.push_subtask_input
+    pub fn push_subtask_file<P: AsRef<Path>>(self, path: P, mode: FileMode) -> Self {
+        self.push_subtask_input(SubtaskInput::File(path.as_ref().into(), mode))
+    }
+
+    /// Pushes subtask data produced chunk by chunk by `chunks` into the buffer
+    ///
+    /// See [`push_subtask_input`] for details.
+    ///
+    /// [`push_subtask_input`]: struct.TaskBuilder.html#method.push_subtask_input
+    pub fn push_subtask_chunks<I>(self, chunks: I) -> Self
+    where
+        I: IntoIterator<Item = Vec<u8>>,
+        I::IntoIter: Send + 'static,
+    {
+        self.push_subtask_input(SubtaskInput::Chunks(Box::new(chunks.into_iter())))
+    }
+
+    /// Pushes subtask data produced chunk by chunk by a [`Stream`] into the buffer
+    ///
+    /// Note that the `stream` is driven to completion on the thread executing
+    /// [`build`], so it must not depend on an event loop running on that thread.
+    /// See [`push_subtask_input`] for details.
+    ///
+    /// [`Stream`]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+    /// [`build`]: struct.TaskBuilder.html#method.build
+    /// [`push_subtask_input`]: struct.TaskBuilder.html#method.push_subtask_input
+    pub fn push_subtask_stream<S>(self, stream: S) -> Self
+    where
+        S: Stream<Item = Vec<u8>> + Send + Unpin + 'static,
+    {
+        self.push_subtask_chunks(block_on_stream(stream))
+    }
+
+    /// Serializes `input` using [`Codec`] `C` and pushes it as subtask data
+    ///
+    /// See [`push_subtask_data`] for details.
+    ///
+    /// [`Codec`]: ../codec/trait.Codec.html
+    /// [`push_subtask_data`]: struct.TaskBuilder.html#method.push_subtask_data
+    pub fn push_subtask_value<C: Codec, I: Serialize>(self, input: &I) -> Result<Self> {
+        let data = C::encode(input)?;
+        Ok(self.push_subtask_data(data))
+    }
+
+    /// Sets the maximum number of threads writing subtasks' data to the
+    /// workspace when building the task
+    ///
+    /// By default, the subtasks are written one after another on the thread
+    /// executing [`build`]. For large numbers of subtasks, especially on network
+    /// filesystems, writing them in parallel can substantially speed up building
+    /// the task.
+    ///
+    /// [`build`]: struct.TaskBuilder.html#method.build
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Sets whether identical subtask inputs should be stored only once
+    ///
+    /// When enabled, in-memory and file inputs with identical contents
+    /// (compared by their SHA-256 digests) are written to the workspace once,
+    /// and hard-linked into the input dirs of the remaining subtasks. Inputs
+    /// streamed from readers, iterators or streams are never deduplicated.
+    ///
+    /// Deduplication is disabled by default.
+    pub fn deduplicate_inputs(mut self, deduplicate_inputs: bool) -> Self {
+        self.deduplicate_inputs = deduplicate_inputs;
+        self
+    }
+
+    /// Sets whether the task's input files should be [verified] against their
+    /// digests recorded during [`build`] before its results are accepted
+    /// by converting it into a [`ComputedTask`]
+    ///
+    /// Verification is disabled by default.
+    ///
+    /// [verified]: struct.Task.html#method.verify_inputs
+    /// [`build`]: struct.TaskBuilder.html#method.build
+    /// [`ComputedTask`]: struct.ComputedTask.html
+    pub fn verify_inputs(mut self, verify_inputs: bool) -> Self {
+        self.verify_inputs = verify_inputs;
+        self
+    }
+
+    /// Sets the [`Redundancy`] with which each subtask is computed
+    ///
+    /// Each replica of a subtask gets its own entry in the task's manifest,
+    /// with its input hard-linked from the subtask's input dir. When the task
+    /// is converted into a [`ComputedTask`], the outputs of the replicas are
+    /// compared, and one [`ComputedSubtask`] is returned per subtask, holding
+    /// the accepted output. If the replicas of any subtask disagree, the
+    /// conversion fails with [`Error::VerificationFailed`].
+    ///
+    /// Note that every replica is billed, so the [estimated cost] of the task
+    /// grows accordingly.
+    ///
+    /// Golem doesn't guarantee that the replicas of a subtask are computed by
+    /// different providers. When the providers are known, i.e., when the task
+    /// is computed with [`compute`], replicas computed by the same provider are
+    /// never taken to agree with each other. Otherwise, e.g., for a task
+    /// [loaded] from its workspace, this can't be checked, and a single
+    /// dishonest provider computing several replicas can pass verification.
+    ///
+    /// [`Redundancy`]: ../redundancy/struct.Redundancy.html
+    /// [`ComputedTask`]: struct.ComputedTask.html
+    /// [`ComputedSubtask`]: struct.ComputedSubtask.html
+    /// [`Error::VerificationFailed`]: ../error/enum.Error.html#variant.VerificationFailed
+    /// [estimated cost]: struct.Task.html#method.estimated_cost
+    /// [`compute`]: ../fn.compute.html
+    /// [loaded]: struct.Task.html#method.load
+    pub fn redundancy(mut self, redundancy: Redundancy) -> Self {
+        self.redundancy = Some(redundancy);
+        self
+    }
+
+    /// Sets whether the subtasks which failed to compute should be returned
+    /// alongside the successful ones
+    ///
+    /// By default, converting the task into a [`ComputedTask`] fails as soon
+    /// as the output of any subtask is missing or can't be verified. With
+    /// partial results, such subtasks are collected in
+    /// [`ComputedTask::failed_subtasks`] instead.
+    ///
+    /// This includes tasks which time out on Golem, e.g., because some of
+    /// their subtasks keep failing on providers: [`compute`] then returns the
+    /// subtasks computed so far, instead of failing with
+    /// [`Error::TaskTimedOut`].
+    ///
+    /// [`ComputedTask`]: struct.ComputedTask.html
+    /// [`compute`]: ../fn.compute.html
+    /// [`Error::TaskTimedOut`]: ../error/enum.Error.html#variant.TaskTimedOut
+    /// [`ComputedTask::failed_subtasks`]: struct.ComputedTask.html#structfield.failed_subtasks
+    pub fn partial_results(mut self, partial_results: bool) -> Self {
+        self.partial_results = partial_results;
+        self
+    }
+
+    /// Attaches metadata with `key` set to `value` to the task
+    ///
+    /// Like [subtask tags], the metadata is not sent to Golem, but stored in
+    /// the workspace. It is passed to the [`ProgressUpdate`] handler with
+    /// every progress update, and returned with the [`ComputedTask`].
+    ///
+    /// # Example:
+    /// ```
+    /// use gwasm_api::task::{GWasmBinary, TaskBuilder};
+    /// use tempfile::tempdir;
+    ///
+    /// let binary = GWasmBinary::new(Vec::new(), Vec::new());
+    /// let workspace = tempdir().unwrap();
+    /// let task = TaskBuilder::try_new(&workspace, binary)
+    ///     .unwrap()
+    ///     .metadata("job_id", "1234")
+    ///     .push_subtask_data(vec![0u8; 10])
+    ///     .subtask_tag("part", "first")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(task.metadata()["job_id"], "1234");
+    /// ```
+    ///
+    /// [subtask tags]: struct.TaskBuilder.html#method.subtask_tag
+    /// [`ProgressUpdate`]: ../trait.ProgressUpdate.html#method.update_with_metadata
+    /// [`ComputedTask`]: struct.ComputedTask.html#structfield.metadata
+    pub fn metadata<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.metadata.insert(key.into(), value.into());
         self
     }
 
     /// Consumes this builder and creates a `Task`
     ///
+    /// See [`build_with_progress`] for details.
+    ///
+    /// [`build_with_progress`]: struct.TaskBuilder.html#method.build_with_progress
+    pub fn build(self) -> Result<Task> {
+        self.build_with_progress(NoProgress)
+    }
+
+    /// Consumes this builder and creates a `Task`, reporting the progress
+    /// of writing subtasks' data to the workspace to `progress_handler`
+    ///
     /// Note that when this method is executed, a corresponding dir
     /// and file structure in the provided `workspace` [`Path`]. For more
     /// details about the dir structure, see [gWasm docs].
     ///
+    /// The dir structure is first created in a staging dir inside the
+    /// workspace, and only moved into place once complete. Thus, if this
+    /// method fails, the workspace is left as it was.
+    ///
+    /// Next to the task's dirs, the task's JSON manifest and a sidecar with
+    /// the rest of its configuration are saved, from which the task can be
+    /// [loaded] again.
+    ///
+    /// [loaded]: struct.Task.html#method.load
     /// [`Path`]: https://doc.rust-lang.org/std/path/struct.Path.html
     /// [gWasm docs]: https://docs.golem.network/#/Products/gWASM/gWASM-tasks?id=inputoutput
-    pub fn build(mut self) -> Result<Task> {
+    pub fn build_with_progress(mut self, progress_handler: impl ProgressUpdate) -> Result<Task> {
         let name = self.name.take().unwrap_or("unknown".to_owned());
         let bid = self.bid.unwrap_or(1.0);
         let timeout = self.timeout.unwrap_or(
@@ -161,65 +724,288 @@ impl<'a> TaskBuilder<'a> {
             Timeout::from_str("00:10:00")
                 .expect("could correctly parse default subtask timeout value"),
         );
+        let replicas = self.redundancy.as_ref().map_or(1, Redundancy::replicas);
+        let estimated = estimate_cost(bid, self.subtasks.len() * replicas, &subtask_timeout);
+        check_budget(estimated, self.budget)?;
+
+        if self.validate_binary {
+            self.binary.validate(&name)?;
+        }
+
+        let incompatibilities = match self.compat_policy {
+            CompatPolicy::Ignore => Vec::new(),
+            CompatPolicy::Warn => compat::analyze_binary(&self.binary)?,
+            CompatPolicy::Reject => {
+                let incompatibilities = compat::analyze_binary(&self.binary)?;
+                if !incompatibilities.is_empty() {
+                    return Err(Error::IncompatibleBinary(incompatibilities));
+                }
+                incompatibilities
+            }
+        };
+
+        let (workspace, temp_workspace) =
+            workspace::prepare(&self.workspace, self.workspace_policy)?;
         let js_name = format!("{}.js", name);
         let wasm_name = format!("{}.wasm", name);
+        let names: Vec<_> = self
+            .subtasks
+            .iter_mut()
+            .enumerate()
+            .map(|(i, pending)| {
+                pending
+                    .name
+                    .take()
+                    .unwrap_or_else(|| default_subtask_name(i))
+            })
+            .collect();
+        check_subtask_names(&names, replicas, &[&js_name, &wasm_name])?;
+        let subtask_dirs: Vec<_> = names
+            .iter()
+            .flat_map(|name| {
+                iter::once(name.clone()).chain((1..replicas).map(move |j| replica_name(name, j)))
+            })
+            .collect();
+        let mut reserved = vec![js_name.as_str(), wasm_name.as_str()];
+        reserved.extend(subtask_dirs.iter().map(String::as_str));
+        check_shared_input_names(&self.shared_inputs, &reserved)?;
         let mut options = Options::new(
             js_name,
             wasm_name,
-            self.input_dir_path.clone(),
-            self.output_dir_path.clone(),
+            workspace::input_dir(&workspace),
+            workspace::output_dir(&workspace),
             self.output_path.clone(),
         );
 
+        // the dir structure is created in a staging dir, and only moved into
+        // the workspace once complete, so that a failure leaves no trace
+        let staging = Staging::new(&workspace)?;
+        let input_dir_path = staging.input_dir();
+        let output_dir_path = staging.output_dir();
+
         // create input dir
-        fs::create_dir(&options.input_dir_path).file_context(&options.input_dir_path)?;
+        fs::create_dir(&input_dir_path).file_context(&input_dir_path)?;
 
         // save JS file
-        let js_filename = options.input_dir_path.join(&options.js_name);
-        fs::write(&js_filename, self.binary.js).file_context(&js_filename)?;
+        let js_filename = input_dir_path.join(&options.js_name);
+        self.binary.js.write_to(&js_filename)?;
 
         // save WASM file
-        let wasm_filename = options.input_dir_path.join(&options.wasm_name);
-        fs::write(&wasm_filename, self.binary.wasm).file_context(&wasm_filename)?;
+        let wasm_filename = input_dir_path.join(&options.wasm_name);
+        self.binary.wasm.write_to(&wasm_filename)?;
+
+        // save shared input files
+        for (shared_name, shared_input) in self.shared_inputs {
+            shared_input.write_to(&input_dir_path.join(shared_name))?;
+        }
 
         // create output dir
-        fs::create_dir(&options.output_dir_path).file_context(&options.output_dir_path)?;
+        fs::create_dir(&output_dir_path).file_context(&output_dir_path)?;
 
         // subtasks
-        for (i, chunk) in self.subtask_data.into_iter().enumerate() {
-            let name = format!("subtask_{}", i);
+        let mut inputs = Vec::new();
+        let mut args = Vec::new();
+        let mut subtask_tags = BTreeMap::new();
+        for (i, pending) in self.subtasks.into_iter().enumerate() {
+            inputs.push(pending.input);
+            args.push(pending.args);
+            if !pending.tags.is_empty() {
+                subtask_tags.insert(names[i].clone(), pending.tags);
+            }
+        }
+        let inputs = if self.deduplicate_inputs {
+            input::deduplicate(inputs)
+        } else {
+            inputs.into_iter().map(Deduplicated::Unique).collect()
+        };
+        let jobs: Vec<_> = inputs.into_iter().zip(args).enumerate().collect();
+        let subtasks = workspace::run_jobs(
+            jobs,
+            self.parallelism,
+            &progress_handler,
+            |(i, (input, args))| {
+                let name = names[i].clone();
+
+                // create input subtask dir
+                let subtask_input_dir_path = input_dir_path.join(&name);
+                fs::create_dir(&subtask_input_dir_path).file_context(&subtask_input_dir_path)?;
+
+                // create output subtask dir
+                let subtask_output_dir_path = output_dir_path.join(&name);
+                fs::create_dir(&subtask_output_dir_path).file_context(&subtask_output_dir_path)?;
 
-            // create input subtask dir
-            let input_dir_path = options.input_dir_path.join(&name);
-            fs::create_dir(&input_dir_path).file_context(&input_dir_path)?;
+                // save input data file, unless it duplicates another subtask's input
+                // which is only linked once all the unique inputs are saved
+                let input_name = input_name(i);
+                let duplicate = match input {
+                    Deduplicated::Unique(chunk) => {
+                        let input_filename = subtask_input_dir_path.join(&input_name);
+                        chunk.write_to(&input_filename)?;
+                        None
+                    }
+                    Deduplicated::Duplicate(primary) => Some(primary),
+                };
 
-            // create output subtask dir
-            let output_dir_path = options.output_dir_path.join(&name);
-            fs::create_dir(&output_dir_path).file_context(&output_dir_path)?;
+                let mut subtask = Subtask::new();
+                subtask.exec_args.push(input_name);
 
-            // save input data file
-            let input_name = format!("in{}", i);
-            let input_filename = input_dir_path.join(&input_name);
-            fs::write(&input_filename, &chunk).file_context(&input_filename)?;
+                let output_name = "out";
+                subtask.exec_args.push(output_name.into());
+                subtask.output_file_paths.push(output_name.into());
 
-            let mut subtask = Subtask::new();
-            subtask.exec_args.push(input_name.into());
+                subtask.exec_args.extend(args);
 
-            let output_name = "out";
-            subtask.exec_args.push(output_name.into());
-            subtask.output_file_paths.push(output_name.into());
+                Ok(((name, subtask), duplicate))
+            },
+        )?;
 
-            options.subtasks.insert(name, subtask);
+        let subtasks = subtasks
+            .into_iter()
+            .enumerate()
+            .map(|(i, (subtask, duplicate))| {
+                if let Some(primary) = duplicate {
+                    let source = input_dir_path
+                        .join(&names[primary])
+                        .join(input_name(primary));
+                    let input_filename = input_dir_path.join(&names[i]).join(input_name(i));
+                    SubtaskInput::File(source, FileMode::HardLink).write_to(&input_filename)?;
+                }
+                Ok(subtask)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // replicas of each subtask share the exec args, and the input
+        // linked from the subtask's input dir; they directly follow the
+        // subtask, so that the subtasks keep the order they were pushed in
+        let mut replica_names = BTreeMap::new();
+        for (i, (name, subtask)) in subtasks.into_iter().enumerate() {
+            options.add_subtask(name.clone(), subtask.clone());
+            let source = input_dir_path.join(&name).join(input_name(i));
+            let names = (1..replicas)
+                .map(|j| {
+                    let replica = replica_name(&name, j);
+                    let replica_input_dir_path = input_dir_path.join(&replica);
+                    fs::create_dir(&replica_input_dir_path)
+                        .file_context(&replica_input_dir_path)?;
+                    let replica_output_dir_path = output_dir_path.join(&replica);
+                    fs::create_dir(&replica_output_dir_path)
+                        .file_context(&replica_output_dir_path)?;
+                    SubtaskInput::File(source.clone(), FileMode::HardLink)
+                        .write_to(&replica_input_dir_path.join(input_name(i)))?;
+                    options.add_subtask(replica.clone(), subtask.clone());
+                    Ok(replica)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if !names.is_empty() {
+                replica_names.insert(name, names);
+            }
         }
 
-        Ok(Task::new(
-            name,
-            bid,
-            self.budget,
-            timeout,
-            subtask_timeout,
-            options,
-        ))
+        // record the digests of all input files, and the rest of the task's
+        // configuration not included in the manifest, in the sidecar
+        let input_digests = digest::digest_dir(&input_dir_path)?;
+        let sidecar = Sidecar {
+            inputs: input_digests,
+            verify_inputs: self.verify_inputs,
+            replicas: replica_names,
+            majority: self.redundancy.iter().any(Redundancy::is_majority),
+            partial_results: self.partial_results,
+            budget_cap: self.budget_cap,
+            metadata: self.metadata,
+            tags: subtask_tags,
+        };
+        sidecar.write(&staging.sidecar())?;
+
+        let mut task = Task::new(name, bid, self.budget, timeout, subtask_timeout, options);
+        task.restore(sidecar);
+        task.redundancy = self.redundancy;
+        task.write_manifest(&staging.manifest())?;
+
+        staging.commit(self.workspace_policy)?;
+
+        task.incompatibilities = incompatibilities;
+        task.temp_workspace = temp_workspace;
+        Ok(task)
+    }
+}
+
+/// Subtask pushed into [`TaskBuilder`] which is yet to be written to the workspace
+#[derive(Debug)]
+struct PendingSubtask {
+    input: SubtaskInput,
+    name: Option<String>,
+    args: Vec<String>,
+    tags: BTreeMap<String, String>,
+}
+
+fn check_shared_input_names(
+    shared_inputs: &[(String, SubtaskInput)],
+    reserved: &[&str],
+) -> Result<()> {
+    for (i, (name, _)) in shared_inputs.iter().enumerate() {
+        let mut components = Path::new(name).components();
+        let is_file_name = matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        );
+        let is_reserved = reserved.contains(&name.as_str());
+        let is_repeated = shared_inputs[..i].iter().any(|(other, _)| other == name);
+        if !is_file_name || is_reserved || is_repeated {
+            return Err(Error::InvalidSharedInputName(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn check_subtask_names(names: &[String], replicas: usize, reserved: &[&str]) -> Result<()> {
+    let mut seen = HashSet::new();
+    for name in names {
+        let mut components = Path::new(name).components();
+        let is_file_name = matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        );
+        if !is_file_name || reserved.contains(&name.as_str()) {
+            return Err(Error::InvalidSubtaskName(name.clone()));
+        }
+        let replicas = (1..replicas).map(|j| replica_name(name, j));
+        for name in iter::once(name.clone()).chain(replicas) {
+            if seen.contains(&name) {
+                return Err(Error::InvalidSubtaskName(name));
+            }
+            seen.insert(name);
+        }
+    }
+    Ok(())
+}
+
+fn default_subtask_name(i: usize) -> String {
+    format!("subtask_{}", i)
+}
+
+fn replica_name(name: &str, j: usize) -> String {
+    format!("{}_replica_{}", name, j)
+}
+
+fn input_name(i: usize) -> String {
+    format!("in{}", i)
+}
+
+struct NoProgress;
+
+impl ProgressUpdate for NoProgress {
+    fn update(&self, _progress: f64) {}
+}
+
+fn estimate_cost(bid: f64, num_subtasks: usize, subtask_timeout: &Timeout) -> f64 {
+    let hours = subtask_timeout.as_secs() as f64 / 3600.0;
+    bid * hours * num_subtasks as f64
+}
+
+fn check_budget(estimated: f64, budget: Option<f64>) -> Result<()> {
+    match budget {
+        Some(budget) if estimated > budget => Err(Error::BudgetExceeded { estimated, budget }),
+        _ => Ok(()),
     }
 }
 
@@ -236,10 +1022,7 @@ impl<'a> TaskBuilder<'a> {
 /// use serde_json::json;
 /// use tempfile::tempdir;
 ///
-/// let binary = GWasmBinary {
-///     js: &[],
-///     wasm: &[],
-/// };
+/// let binary = GWasmBinary::new(Vec::new(), Vec::new());
 /// let workspace = tempdir().unwrap();
 /// let task = TaskBuilder::try_new(&workspace, binary).unwrap().build().unwrap();
 /// let json_manifest = json!(task);
@@ -247,7 +1030,7 @@ impl<'a> TaskBuilder<'a> {
 ///
 /// [gWasm Task JSON]: https://docs.golem.network/#/Products/gWASM/gWASM-tasks?id=task-json
 /// [`TaskBuilder`]: ../task/struct.TaskBuilder.html
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Task {
     #[serde(rename = "type")]
     task_type: String,
@@ -258,6 +1041,30 @@ pub struct Task {
     timeout: Timeout,
     subtask_timeout: Timeout,
     options: Options,
+    #[serde(skip)]
+    budget_cap: Option<f64>,
+    #[serde(skip)]
+    incompatibilities: Vec<Incompatibility>,
+    #[serde(skip)]
+    temp_workspace: Option<Arc<TempWorkspace>>,
+    #[serde(skip)]
+    input_digests: BTreeMap<String, Digest>,
+    #[serde(skip)]
+    verify_inputs_on_accept: bool,
+    #[serde(skip)]
+    redundancy: Option<Redundancy>,
+    #[serde(skip)]
+    replica_names: BTreeMap<String, Vec<String>>,
+    #[serde(skip)]
+    subtask_failures: Vec<SubtaskFailure>,
+    #[serde(skip)]
+    subtask_nodes: BTreeMap<String, String>,
+    #[serde(skip)]
+    partial_results: bool,
+    #[serde(skip)]
+    metadata: BTreeMap<String, String>,
+    #[serde(skip)]
+    subtask_tags: BTreeMap<String, BTreeMap<String, String>>,
 }
 
 impl Task {
@@ -278,9 +1085,96 @@ impl Task {
             timeout,
             subtask_timeout,
             options,
+            budget_cap: None,
+            incompatibilities: Vec::new(),
+            temp_workspace: None,
+            input_digests: BTreeMap::new(),
+            verify_inputs_on_accept: false,
+            redundancy: None,
+            replica_names: BTreeMap::new(),
+            subtask_failures: Vec::new(),
+            subtask_nodes: BTreeMap::new(),
+            partial_results: false,
+            metadata: BTreeMap::new(),
+            subtask_tags: BTreeMap::new(),
         }
     }
 
+    /// Loads the `Task` built by [`TaskBuilder::build`] in `workspace`
+    ///
+    /// The task is read from the JSON manifest and the sidecar which
+    /// [`TaskBuilder::build`] saves in the workspace next to the task's dirs,
+    /// which allows computing the task, or collecting its results, in
+    /// another process than the one which built it. Note that a custom
+    /// [`Redundancy::comparator`] cannot be saved, so the outputs of
+    /// the replicas of a loaded task are compared byte by byte.
+    ///
+    /// For a task built with [`WorkspacePolicy::Temp`], `workspace` is the
+    /// temporary workspace created by the builder.
+    ///
+    /// # Example:
+    /// ```
+    /// use gwasm_api::task::{GWasmBinary, Task, TaskBuilder};
+    /// use tempfile::tempdir;
+    ///
+    /// let binary = GWasmBinary::new(Vec::new(), Vec::new());
+    /// let workspace = tempdir().unwrap();
+    /// let task = TaskBuilder::try_new(&workspace, binary)
+    ///     .unwrap()
+    ///     .name("example")
+    ///     .push_subtask_data(vec![0u8; 10])
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let loaded = Task::load(&workspace).unwrap();
+    /// assert_eq!(loaded.name(), "example");
+    /// assert_eq!(loaded.options().subtasks().count(), 1);
+    /// ```
+    ///
+    /// [`TaskBuilder::build`]: struct.TaskBuilder.html#method.build
+    /// [`Redundancy::comparator`]: ../redundancy/struct.Redundancy.html#method.comparator
+    /// [`WorkspacePolicy::Temp`]: ../workspace/enum.WorkspacePolicy.html#variant.Temp
+    pub fn load<P: AsRef<Path>>(workspace: P) -> Result<Self> {
+        let workspace = workspace.as_ref();
+        let manifest = workspace::manifest(workspace);
+        let file = File::open(&manifest).file_context(&manifest)?;
+        let mut task: Self = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| Error::CodecError(e.into()))?;
+        task.restore(Sidecar::read(&workspace::sidecar(workspace))?);
+        Ok(task)
+    }
+
+    /// Restores the task's configuration recorded in the sidecar
+    fn restore(&mut self, sidecar: Sidecar) {
+        if let Some(replicas) = sidecar.replicas.values().next() {
+            let redundancy = Redundancy::new(replicas.len() + 1).majority(sidecar.majority);
+            self.redundancy = Some(redundancy);
+        }
+        self.input_digests = sidecar.inputs;
+        self.verify_inputs_on_accept = sidecar.verify_inputs;
+        self.replica_names = sidecar.replicas;
+        self.partial_results = sidecar.partial_results;
+        self.budget_cap = sidecar.budget_cap;
+        self.metadata = sidecar.metadata;
+        self.subtask_tags = sidecar.tags;
+    }
+
+    /// Writes the task's JSON manifest to `path`
+    fn write_manifest(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).file_context(path)?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self).map_err(|e| Error::CodecError(e.into()))?;
+        writer.flush().file_context(path)
+    }
+
+    /// Task's JSON manifest, exactly as sent to Golem when the task is
+    /// created (see [gWasm Task JSON])
+    ///
+    /// [gWasm Task JSON]: https://docs.golem.network/#/Products/gWASM/gWASM-tasks?id=task-json
+    pub fn manifest(&self) -> serde_json::Value {
+        serde_json::json!(self)
+    }
+
     /// Task's name
     pub fn name(&self) -> &str {
         &self.name
@@ -296,6 +1190,168 @@ impl Task {
         self.budget
     }
 
+    /// Task's hard spending cap
+    pub fn budget_cap(&self) -> Option<f64> {
+        self.budget_cap
+    }
+
+    /// Task's [metadata](struct.TaskBuilder.html#method.metadata)
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// [Tags](struct.TaskBuilder.html#method.subtask_tag) of the subtask
+    /// called `name`, if any
+    pub fn subtask_tags(&self, name: &str) -> Option<&BTreeMap<String, String>> {
+        self.subtask_tags.get(name)
+    }
+
+    /// Estimated worst-case cost of computing the task
+    ///
+    /// The estimate assumes that every subtask is billed at the task's bid
+    /// (per hour) for the full duration of the subtask's
+    /// [`Timeout`](../timeout/struct.Timeout.html).
+    ///
+    /// # Example:
+    /// ```
+    /// use gwasm_api::task::{GWasmBinary, TaskBuilder};
+    /// use gwasm_api::timeout::Timeout;
+    /// use std::str::FromStr;
+    /// use tempfile::tempdir;
+    ///
+    /// let binary = GWasmBinary::new(Vec::new(), Vec::new());
+    /// let workspace = tempdir().unwrap();
+    /// let builder = TaskBuilder::try_new(&workspace, binary)
+    ///     .unwrap()
+    ///     .bid(2.0)
+    ///     .subtask_timeout(Timeout::from_str("00:30:00").unwrap())
+    ///     .push_subtask_data(vec![0u8; 10])
+    ///     .push_subtask_data(vec![1u8; 10]);
+    /// assert!(builder.budget(1.0).build().is_err());
+    /// ```
+    ///
+    /// ```
+    /// # use gwasm_api::task::{GWasmBinary, TaskBuilder};
+    /// # use gwasm_api::timeout::Timeout;
+    /// # use std::str::FromStr;
+    /// # use tempfile::tempdir;
+    /// # let binary = GWasmBinary::new(Vec::new(), Vec::new());
+    /// # let workspace = tempdir().unwrap();
+    /// let task = TaskBuilder::try_new(&workspace, binary)
+    ///     .unwrap()
+    ///     .bid(2.0)
+    ///     .subtask_timeout(Timeout::from_str("00:30:00").unwrap())
+    ///     .push_subtask_data(vec![0u8; 10])
+    ///     .push_subtask_data(vec![1u8; 10])
+    ///     .budget(2.0)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(task.estimated_cost(), 2.0);
+    /// ```
+    pub fn estimated_cost(&self) -> f64 {
+        estimate_cost(self.bid, self.options.subtasks.len(), &self.subtask_timeout)
+    }
+
+    /// Checks that the [estimated cost] of the task does not exceed its budget
+    ///
+    /// [estimated cost]: struct.Task.html#method.estimated_cost
+    pub fn check_budget(&self) -> Result<()> {
+        check_budget(self.estimated_cost(), self.budget)
+    }
+
+    /// Returns an [`Iterator`] over the imports of the task's Wasm binary
+    /// unsupported by the sp-wasm sandbox
+    ///
+    /// The imports are only recorded with [`CompatPolicy::Warn`].
+    ///
+    /// [`Iterator`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html
+    /// [`CompatPolicy::Warn`]: ../compat/enum.CompatPolicy.html#variant.Warn
+    pub fn incompatibilities(&self) -> impl Iterator<Item = &Incompatibility> {
+        self.incompatibilities.iter()
+    }
+
+    /// Returns an [`Iterator`] over the [`Digest`]s of the task's input files
+    /// recorded by [`TaskBuilder::build`]
+    ///
+    /// The files are keyed by their path relative to the task's input dir,
+    /// with components separated by `/`.
+    ///
+    /// [`Iterator`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html
+    /// [`Digest`]: ../digest/struct.Digest.html
+    /// [`TaskBuilder::build`]: struct.TaskBuilder.html#method.build
+    pub fn input_digests(&self) -> impl Iterator<Item = (&str, &Digest)> {
+        self.input_digests
+            .iter()
+            .map(|(name, digest)| (name.as_str(), digest))
+    }
+
+    /// Recomputes the digests of the task's input files, checking that the
+    /// files haven't changed since the task was built
+    ///
+    /// Fails with [`Error::DigestMismatch`] on the first modified file.
+    ///
+    /// [`Error::DigestMismatch`]: ../error/enum.Error.html#variant.DigestMismatch
+    pub fn verify_inputs(&self) -> Result<()> {
+        digest::verify_dir(self.options.input_dir_path(), &self.input_digests)
+    }
+
+    /// Records the failures of the task's subtasks on providers, to be
+    /// attached to the [`ComputedSubtask`]s
+    ///
+    /// [`ComputedSubtask`]: struct.ComputedSubtask.html
+    pub(crate) fn set_subtask_failures(&mut self, failures: Vec<SubtaskFailure>) {
+        self.subtask_failures = failures;
+    }
+
+    /// Returns `true` if the subtasks which failed to compute are returned
+    /// alongside the successful ones
+    pub(crate) fn has_partial_results(&self) -> bool {
+        self.partial_results
+    }
+
+    /// Records the provider nodes which computed the task's subtasks, keyed
+    /// by the subtasks' names, so that replicas computed by the same node
+    /// are not taken to agree
+    pub(crate) fn set_subtask_nodes(&mut self, nodes: BTreeMap<String, String>) {
+        self.subtask_nodes = nodes;
+    }
+
+    /// Replaces the task's timeouts, and rewrites the JSON manifest in the
+    /// task's workspace, if any, so that the [loaded] task has them too
+    ///
+    /// [loaded]: struct.Task.html#method.load
+    pub(crate) fn set_timeouts(
+        &mut self,
+        timeout: Timeout,
+        subtask_timeout: Timeout,
+    ) -> Result<()> {
+        self.timeout = timeout;
+        self.subtask_timeout = subtask_timeout;
+
+        let manifest = match self.options.input_dir_path().parent() {
+            Some(workspace) => workspace::manifest(workspace),
+            None => return Ok(()),
+        };
+        if manifest.is_file() {
+            // the new manifest replaces the old one only once fully written
+            let staged = manifest.with_extension("json.tmp");
+            self.write_manifest(&staged)?;
+            fs::rename(&staged, &manifest).file_context(&manifest)?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the subtask called `name` is a [replica] of
+    /// another subtask
+    ///
+    /// [replica]: struct.TaskBuilder.html#method.redundancy
+    pub(crate) fn is_replica(&self, name: &str) -> bool {
+        self.replica_names
+            .values()
+            .flatten()
+            .any(|replica| replica == name)
+    }
+
     /// Task's [`Timeout`](../timeout/struct.Timeout.html) value
     pub fn timeout(&self) -> &Timeout {
         &self.timeout
@@ -310,6 +1366,18 @@ impl Task {
     pub fn options(&self) -> &Options {
         &self.options
     }
+
+    /// Prevents the task's temporary workspace from being removed once the
+    /// task is dropped
+    ///
+    /// Has no effect unless the task was built with [`WorkspacePolicy::Temp`].
+    ///
+    /// [`WorkspacePolicy::Temp`]: ../workspace/enum.WorkspacePolicy.html#variant.Temp
+    pub fn keep_workspace(&self) {
+        if let Some(temp_workspace) = &self.temp_workspace {
+            temp_workspace.keep();
+        }
+    }
 }
 
 /// Struct representing gWasm task's options substructure
@@ -319,7 +1387,7 @@ impl Task {
 /// with the [`Task`] structure.
 ///
 /// [`Task`]: ../task/struct.Task.html
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Options {
     js_name: String,
     wasm_name: String,
@@ -329,7 +1397,7 @@ pub struct Options {
     output_dir_path: PathBuf,
     #[serde(skip_serializing_if = "Option::is_none")]
     output_path: Option<PathBuf>,
-    subtasks: BTreeMap<String, Subtask>,
+    subtasks: Subtasks,
 }
 
 impl Options {
@@ -347,7 +1415,7 @@ impl Options {
             input_dir_path: input_dir_path.into(),
             output_dir_path: output_dir_path.into(),
             output_path: output_path.map(Into::into),
-            subtasks: BTreeMap::new(),
+            subtasks: Subtasks::default(),
         }
     }
 
@@ -377,27 +1445,94 @@ impl Options {
         self.output_path.as_ref().map(AsRef::as_ref)
     }
 
-    /// Returns an [`Iterator`] over created [`Subtask`]'s
+    /// Returns an [`Iterator`] over created [`Subtask`]'s, in the order
+    /// they were added
     ///
     /// [`Subtask`]: ../task/struct.Subtask.html
     /// [`Iterator`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html
     pub fn subtasks(&self) -> impl Iterator<Item = (&str, &Subtask)> {
-        self.subtasks
-            .iter()
-            .map(|(name, subtask)| (name.as_str(), subtask))
+        self.subtasks.iter()
     }
 
     /// Adds a new [`Subtask`] under the given name.
+    ///
+    /// A subtask already added under the same name is replaced, but keeps
+    /// its position.
     pub fn add_subtask(&mut self, name: String, subtask: Subtask) {
         self.subtasks.insert(name, subtask);
     }
 }
 
+/// [`Subtask`]s keyed by their names, along with the order they were added in
+///
+/// Golem expects the subtasks as a map, so they are serialized as one, with
+/// the entries in insertion order, which is also restored when loading
+/// a task.
+///
+/// [`Subtask`]: ../task/struct.Subtask.html
+#[derive(Debug, Clone, Default)]
+struct Subtasks {
+    order: Vec<String>,
+    subtasks: BTreeMap<String, Subtask>,
+}
+
+impl Subtasks {
+    fn insert(&mut self, name: String, subtask: Subtask) {
+        if !self.subtasks.contains_key(&name) {
+            self.order.push(name.clone());
+        }
+        self.subtasks.insert(name, subtask);
+    }
+
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&str, &Subtask)> {
+        self.order
+            .iter()
+            .map(move |name| (name.as_str(), &self.subtasks[name]))
+    }
+}
+
+impl Serialize for Subtasks {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for Subtasks {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct SubtasksVisitor;
+
+        impl<'de> Visitor<'de> for SubtasksVisitor {
+            type Value = Subtasks;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map of subtasks")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<Subtasks, A::Error> {
+                let mut subtasks = Subtasks::default();
+                while let Some((name, subtask)) = map.next_entry()? {
+                    subtasks.insert(name, subtask);
+                }
+                Ok(subtasks)
+            }
+        }
+
+        deserializer.deserialize_map(SubtasksVisitor)
+    }
+}
+
 /// Struct representing gWasm task's subtask substructure
 ///
 /// Stores information such as the execution arguments for the Wasm binary,
 /// and output file paths for the computed results.
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Subtask {
     /// CLI arguments to be passed for this subtask
     pub exec_args: Vec<String>,
@@ -442,10 +1577,7 @@ impl Subtask {
 /// use tempfile::tempdir;
 /// use std::convert::TryInto;
 ///
-/// let binary = GWasmBinary {
-///     js: &[],
-///     wasm: &[],
-/// };
+/// let binary = GWasmBinary::new(Vec::new(), Vec::new());
 /// let workspace = tempdir().unwrap();
 /// let task = TaskBuilder::try_new(&workspace, binary).unwrap().build().unwrap();
 /// let computed_task: Result<ComputedTask, _> = task.try_into();
@@ -471,13 +1603,47 @@ pub struct ComputedTask {
     /// [`Timeout`]: ../timeout/struct.Timeout.html
     pub subtask_timeout: Timeout,
     /// [`Vec`] of [`ComputedSubtask`]s, ordered by subtask data insertion
-    /// using [`TaskBuilder::push_subtask_data`]
+    /// using [`TaskBuilder::push_subtask_data`], regardless of the subtasks'
+    /// names
+    ///
+    /// With [partial results], only the successfully computed subtasks are
+    /// included.
     ///
     /// [`Vec`]: https://doc.rust-lang.org/std/vec/struct.Vec.html
     /// [`ComputedSubtask`]: ../task/struct.ComputedSubtask.html
     /// [`TaskBuilder::push_subtask_data`]:
     /// ../task/struct.TaskBuilder.html#method.push_subtask_data
+    /// [partial results]: ../task/struct.TaskBuilder.html#method.partial_results
     pub subtasks: Vec<ComputedSubtask>,
+    /// [`Vec`] of [`FailedSubtask`]s, ordered by subtask data insertion, like
+    /// [`subtasks`]
+    ///
+    /// Always empty unless the task was built with [partial results].
+    ///
+    /// [`Vec`]: https://doc.rust-lang.org/std/vec/struct.Vec.html
+    /// [`FailedSubtask`]: ../task/struct.FailedSubtask.html
+    /// [`subtasks`]: struct.ComputedTask.html#structfield.subtasks
+    /// [partial results]: ../task/struct.TaskBuilder.html#method.partial_results
+    pub failed_subtasks: Vec<FailedSubtask>,
+    /// Task's [metadata](../task/struct.TaskBuilder.html#method.metadata)
+    pub metadata: BTreeMap<String, String>,
+    // keeps the temporary workspace holding the outputs alive
+    _temp_workspace: Option<Arc<TempWorkspace>>,
+}
+
+impl ComputedTask {
+    /// Deserializes the output of every subtask using [`Codec`] `C`
+    ///
+    /// The outputs are returned in the same order as [`subtasks`].
+    ///
+    /// [`Codec`]: ../codec/trait.Codec.html
+    /// [`subtasks`]: struct.ComputedTask.html#structfield.subtasks
+    pub fn decode_outputs<C: Codec, O: DeserializeOwned>(&mut self) -> Result<Vec<O>> {
+        self.subtasks
+            .iter_mut()
+            .map(ComputedSubtask::decode::<C, O>)
+            .collect()
+    }
 }
 
 /// Struct representing computed subtask
@@ -499,46 +1665,587 @@ pub struct ComputedSubtask {
     /// [`Subtask::output_file_paths`]: ../task/struct.Subtask.html#method.output_file_paths
     /// [`BufReader`]: https://doc.rust-lang.org/std/io/struct.BufReader.html
     pub data: BTreeMap<PathBuf, BufReader<File>>,
+    /// [`BTreeMap`] of the [`Digest`]s of the files in [`data`], keyed by
+    /// the same paths
+    ///
+    /// [`BTreeMap`]: https://doc.rust-lang.org/std/collections/struct.BTreeMap.html
+    /// [`Digest`]: ../digest/struct.Digest.html
+    /// [`data`]: struct.ComputedSubtask.html#structfield.data
+    pub digests: BTreeMap<PathBuf, Digest>,
+    /// Diagnostics of the subtask's (or its replicas') failures on
+    /// providers, which Golem recovered from by recomputing it
+    pub failures: Vec<SubtaskFailure>,
     /// Subtask's name
     pub name: String,
+    /// Subtask's [tags](../task/struct.TaskBuilder.html#method.subtask_tag)
+    pub tags: BTreeMap<String, String>,
+}
+
+impl ComputedSubtask {
+    /// Deserializes the subtask's output using [`Codec`] `C`
+    ///
+    /// [`Codec`]: ../codec/trait.Codec.html
+    pub fn decode<C: Codec, O: DeserializeOwned>(&mut self) -> Result<O> {
+        match self.data.values_mut().next() {
+            Some(reader) => C::decode(reader),
+            None => Err(Error::MissingOutput(self.name.clone())),
+        }
+    }
+}
+
+/// Struct representing a subtask which failed to compute, returned with
+/// [partial results]
+///
+/// [partial results]: ../task/struct.TaskBuilder.html#method.partial_results
+#[derive(Debug)]
+pub struct FailedSubtask {
+    /// Subtask's name
+    pub name: String,
+    /// The reason the subtask failed
+    pub error: Error,
 }
 
 impl TryFrom<Task> for ComputedTask {
     type Error = Error;
 
     fn try_from(task: Task) -> Result<Self> {
-        let name = task.name;
-        let bid = task.bid;
-        let timeout = task.timeout;
-        let subtask_timeout = task.subtask_timeout;
+        if task.verify_inputs_on_accept {
+            task.verify_inputs()?;
+        }
+
         let mut computed_subtasks = Vec::new();
+        let mut failed_subtasks = Vec::new();
+        let mut disagreements = Vec::new();
+        let replicas: HashSet<&str> = task
+            .replica_names
+            .values()
+            .flatten()
+            .map(String::as_str)
+            .collect();
 
         for (s_name, subtask) in task.options.subtasks() {
-            let output_dir = task.options.output_dir_path().join(s_name);
-            let mut computed_subtask = ComputedSubtask {
-                data: BTreeMap::new(),
-                name: String::from(s_name),
-            };
-
-            for out_path in subtask.output_file_paths() {
-                let relative_path = out_path
-                    .strip_prefix(Component::RootDir)
-                    .unwrap_or(out_path);
-                let fname = output_dir.join(relative_path);
-                let f = File::open(&fname).file_context(&fname)?;
-                let reader = BufReader::new(f);
-                computed_subtask.data.insert(out_path.into(), reader);
+            if replicas.contains(s_name) {
+                continue;
+            }
+
+            match compute_subtask(&task, s_name, subtask) {
+                Ok(computed_subtask) => computed_subtasks.push(computed_subtask),
+                Err(error) if task.partial_results => failed_subtasks.push(FailedSubtask {
+                    name: String::from(s_name),
+                    error,
+                }),
+                Err(Error::VerificationFailed(disagreement)) => disagreements.extend(disagreement),
+                Err(error) => return Err(error),
             }
+        }
 
-            computed_subtasks.push(computed_subtask);
+        if !disagreements.is_empty() {
+            return Err(Error::VerificationFailed(disagreements));
         }
 
         Ok(Self {
-            name,
-            bid,
-            timeout,
-            subtask_timeout,
+            name: task.name,
+            bid: task.bid,
+            timeout: task.timeout,
+            subtask_timeout: task.subtask_timeout,
             subtasks: computed_subtasks,
+            failed_subtasks,
+            metadata: task.metadata,
+            _temp_workspace: task.temp_workspace,
         })
     }
 }
+
+/// Collects the outputs of the subtask called `name`, verifying the outputs
+/// of its replicas, if any
+fn compute_subtask(task: &Task, name: &str, subtask: &Subtask) -> Result<ComputedSubtask> {
+    let replica_names = task.replica_names.get(name);
+    let failures: Vec<_> = task
+        .subtask_failures
+        .iter()
+        .filter(|failure| {
+            let mut names =
+                iter::once(name).chain(replica_names.into_iter().flatten().map(String::as_str));
+            names.any(|name| failure.subtask.as_deref() == Some(name))
+        })
+        .cloned()
+        .collect();
+
+    let mut output_files = subtask_output_files(&task.options, name, subtask);
+    if let (Some(redundancy), Some(names)) = (&task.redundancy, replica_names) {
+        let mut outputs = vec![output_files];
+        for name in names {
+            outputs.push(subtask_output_files(&task.options, name, subtask));
+        }
+        let nodes: Vec<_> = iter::once(name)
+            .chain(names.iter().map(String::as_str))
+            .map(|name| task.subtask_nodes.get(name).map(String::as_str))
+            .collect();
+        match redundancy.verify(name, &outputs, &nodes)? {
+            Ok(accepted) => output_files = outputs.swap_remove(accepted),
+            Err(disagreement) => return Err(Error::VerificationFailed(vec![disagreement])),
+        }
+    }
+
+    let mut computed_subtask = ComputedSubtask {
+        data: BTreeMap::new(),
+        digests: BTreeMap::new(),
+        failures,
+        name: String::from(name),
+        tags: task.subtask_tags(name).cloned().unwrap_or_default(),
+    };
+
+    for (out_path, fname) in output_files {
+        let f = match File::open(&fname) {
+            Ok(f) => f,
+            Err(_) if !computed_subtask.failures.is_empty() => {
+                return Err(Error::SubtaskFailed {
+                    subtask: computed_subtask.name,
+                    failures: computed_subtask.failures,
+                })
+            }
+            Err(err) => return Err(Error::FileError(err, fname)),
+        };
+        let reader = BufReader::new(f);
+        computed_subtask.data.insert(out_path.clone(), reader);
+        let digest = Digest::of_file(&fname)?;
+        computed_subtask.digests.insert(out_path, digest);
+    }
+
+    Ok(computed_subtask)
+}
+
+/// Maps the output file paths of `subtask` to the actual files in the
+/// output dir of the subtask called `name`
+fn subtask_output_files(
+    options: &Options,
+    name: &str,
+    subtask: &Subtask,
+) -> BTreeMap<PathBuf, PathBuf> {
+    let output_dir = options.output_dir_path().join(name);
+    subtask
+        .output_file_paths()
+        .map(|out_path| {
+            let relative_path = out_path
+                .strip_prefix(Component::RootDir)
+                .unwrap_or(out_path);
+            (out_path.to_owned(), output_dir.join(relative_path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codec::Json;
+    use serde_json::json;
+
+    fn assert_send<T: Send + 'static>() {}
+
+    #[test]
+    fn owned_builder_is_send() {
+        assert_send::<TaskBuilder<'static>>();
+    }
+
+    #[test]
+    fn binary_from_files() {
+        let build_dir = tempfile::tempdir().unwrap();
+        let js = build_dir.path().join("app.js");
+        let wasm = build_dir.path().join("app.wasm");
+        fs::write(&js, b"").unwrap();
+        assert!(GWasmBinary::from_files(&js, &wasm).is_err());
+
+        fs::create_dir(&wasm).unwrap();
+        match GWasmBinary::from_prefix(build_dir.path().join("app")) {
+            Err(Error::FileError(_, path)) => assert_eq!(path, wasm),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        fs::remove_dir(&wasm).unwrap();
+        fs::write(&wasm, b"").unwrap();
+        assert!(GWasmBinary::from_files(&js, &wasm).is_ok());
+    }
+
+    #[test]
+    fn shared_inputs() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .name("app")
+            .push_shared_input("table.bin", vec![7u8; 16])
+            .push_subtask_data(vec![0u8; 16])
+            .subtask_args(vec!["--table", "table.bin"])
+            .build()
+            .unwrap();
+
+        let input_dir = task.options().input_dir_path();
+        assert_eq!(
+            fs::read(input_dir.join("table.bin")).unwrap(),
+            vec![7u8; 16]
+        );
+        let (_, subtask) = task.options().subtasks().next().unwrap();
+        assert_eq!(
+            subtask.exec_args().collect::<Vec<_>>(),
+            vec!["in0", "out", "--table", "table.bin"]
+        );
+    }
+
+    #[test]
+    fn invalid_shared_input_names() {
+        for name in &["app.wasm", "app.js", "../table.bin", "dir/table.bin", ""] {
+            let workspace = tempfile::tempdir().unwrap();
+            let binary = GWasmBinary::new(Vec::new(), Vec::new());
+            let result = TaskBuilder::try_new(&workspace, binary)
+                .unwrap()
+                .name("app")
+                .push_shared_input(*name, vec![7u8; 16])
+                .build();
+            match result {
+                Err(Error::InvalidSharedInputName(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", name, other),
+            }
+        }
+    }
+
+    #[test]
+    fn shared_input_and_subtask_names() {
+        let build = |shared_name: &str| {
+            let workspace = tempfile::tempdir().unwrap();
+            let binary = GWasmBinary::new(Vec::new(), Vec::new());
+            TaskBuilder::try_new(&workspace, binary)
+                .unwrap()
+                .name("app")
+                .redundancy(Redundancy::new(2))
+                .push_shared_input(shared_name, vec![7u8; 16])
+                .push_subtask_data(vec![0u8; 16])
+                .push_subtask_data(vec![1u8; 16])
+                .subtask_name("frame")
+                .build()
+                .map(|_| ())
+        };
+        assert!(build("subtask_1").is_ok());
+        assert!(build("subtask_table.bin").is_ok());
+        for name in &[
+            "subtask_0",
+            "subtask_0_replica_1",
+            "frame",
+            "frame_replica_1",
+        ] {
+            match build(name) {
+                Err(Error::InvalidSharedInputName(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", name, other),
+            }
+        }
+    }
+
+    #[test]
+    fn input_and_output_digests() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .name("app")
+            .verify_inputs(true)
+            .push_subtask_data(vec![0u8; 16])
+            .build()
+            .unwrap();
+
+        let digests: BTreeMap<_, _> = task.input_digests().collect();
+        assert_eq!(
+            digests.keys().collect::<Vec<_>>(),
+            vec![&"app.js", &"app.wasm", &"subtask_0/in0"]
+        );
+        assert_eq!(digests["subtask_0/in0"], &Digest::of_bytes(&[0u8; 16]));
+
+        let output_dir = task.options().output_dir_path().join("subtask_0");
+        fs::write(output_dir.join("out"), b"output").unwrap();
+        let computed_task = ComputedTask::try_from(task.clone()).unwrap();
+        assert_eq!(
+            computed_task.subtasks[0].digests[Path::new("out")],
+            Digest::of_bytes(b"output")
+        );
+
+        let input_dir = task.options().input_dir_path().join("subtask_0");
+        fs::write(input_dir.join("in0"), b"tampered").unwrap();
+        match ComputedTask::try_from(task) {
+            Err(Error::DigestMismatch { .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn redundant_subtasks() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .redundancy(Redundancy::new(3).majority(true))
+            .push_subtask_data(vec![0u8; 16])
+            .push_subtask_data(vec![1u8; 16])
+            .build()
+            .unwrap();
+        assert_eq!(task.options().subtasks().count(), 6);
+
+        let output_dir = task.options().output_dir_path();
+        for (name, _) in task.options().subtasks() {
+            // the first replica of subtask_1 is faulty
+            let output = if name == "subtask_1" { "bad" } else { "good" };
+            fs::write(output_dir.join(name).join("out"), output).unwrap();
+        }
+        let computed_task = ComputedTask::try_from(task.clone()).unwrap();
+        assert_eq!(computed_task.subtasks.len(), 2);
+        assert_eq!(
+            computed_task.subtasks[1].digests[Path::new("out")],
+            Digest::of_bytes(b"good")
+        );
+
+        fs::write(output_dir.join("subtask_1_replica_1").join("out"), "worse").unwrap();
+        match ComputedTask::try_from(task) {
+            Err(Error::VerificationFailed(disagreements)) => {
+                assert_eq!(disagreements.len(), 1);
+                assert_eq!(disagreements[0].subtask, "subtask_1");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn load_built_task() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .name("loaded")
+            .budget_cap(5.0)
+            .verify_inputs(true)
+            .redundancy(Redundancy::new(2))
+            .push_subtask_data(vec![0u8; 16])
+            .build()
+            .unwrap();
+
+        let loaded = Task::load(&workspace).unwrap();
+        assert_eq!(json!(loaded), json!(task));
+        assert_eq!(loaded.budget_cap(), Some(5.0));
+        assert!(loaded.verify_inputs_on_accept);
+        assert!(loaded.is_replica("subtask_0_replica_1"));
+        assert_eq!(
+            loaded.redundancy.as_ref().map(Redundancy::replicas),
+            Some(2)
+        );
+        assert!(loaded.input_digests().eq(task.input_digests()));
+    }
+
+    #[test]
+    fn metadata_and_tags() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .metadata("job_id", "1234")
+            .push_subtask_data(vec![0u8; 16])
+            .push_subtask_data(vec![1u8; 16])
+            .subtask_tag("part", "second")
+            .build()
+            .unwrap();
+        assert!(!json!(task).to_string().contains("1234"));
+
+        let output_dir = task.options().output_dir_path();
+        for (name, _) in task.options().subtasks() {
+            fs::write(output_dir.join(name).join("out"), name).unwrap();
+        }
+        let computed_task = ComputedTask::try_from(Task::load(&workspace).unwrap()).unwrap();
+        assert_eq!(computed_task.metadata["job_id"], "1234");
+        assert!(computed_task.subtasks[0].tags.is_empty());
+        assert_eq!(computed_task.subtasks[1].tags["part"], "second");
+    }
+
+    #[test]
+    fn subtask_order() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = (0..12u8)
+            .fold(
+                TaskBuilder::try_new(&workspace, binary).unwrap(),
+                |builder, i| builder.push_subtask_data(vec![i; 16]),
+            )
+            .build()
+            .unwrap();
+
+        let output_dir = task.options().output_dir_path();
+        for (name, _) in task.options().subtasks() {
+            fs::write(output_dir.join(name).join("out"), name).unwrap();
+        }
+        let computed_task = ComputedTask::try_from(Task::load(&workspace).unwrap()).unwrap();
+        let names: Vec<_> = computed_task
+            .subtasks
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        let expected: Vec<_> = (0..12).map(default_subtask_name).collect();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn decode_outputs_in_subtask_order() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = (0..12u32)
+            .fold(
+                TaskBuilder::try_new(&workspace, binary).unwrap(),
+                |builder, i| builder.push_subtask_value::<Json, _>(&i).unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        // emulate the Wasm app echoing its input
+        for (name, subtask) in task.options().subtasks() {
+            let input_name = subtask.exec_args().next().unwrap();
+            let input = task.options().input_dir_path().join(name).join(input_name);
+            let output = task.options().output_dir_path().join(name).join("out");
+            fs::copy(input, output).unwrap();
+        }
+        let mut computed_task = ComputedTask::try_from(task).unwrap();
+        let outputs: Vec<u32> = computed_task.decode_outputs::<Json, _>().unwrap();
+        assert_eq!(outputs, (0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn custom_subtask_names() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .redundancy(Redundancy::new(2))
+            .push_subtask_data(vec![0u8; 16])
+            .subtask_name("zebra")
+            .push_subtask_data(vec![1u8; 16])
+            .push_subtask_data(vec![2u8; 16])
+            .subtask_name("aardvark")
+            .build()
+            .unwrap();
+        let names: Vec<_> = task.options().subtasks().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "zebra",
+                "zebra_replica_1",
+                "subtask_1",
+                "subtask_1_replica_1",
+                "aardvark",
+                "aardvark_replica_1"
+            ]
+        );
+        assert!(workspace
+            .path()
+            .join("in")
+            .join("zebra")
+            .join("in0")
+            .is_file());
+
+        for names in &[
+            &["../zebra"][..],
+            &["dir/zebra"],
+            &[""],
+            &["app.wasm"],
+            &["zebra", "zebra"],
+            &["zebra", "zebra_replica_1"],
+        ] {
+            let workspace = tempfile::tempdir().unwrap();
+            let binary = GWasmBinary::new(Vec::new(), Vec::new());
+            let builder = TaskBuilder::try_new(&workspace, binary)
+                .unwrap()
+                .name("app")
+                .redundancy(Redundancy::new(2))
+                .push_shared_input("table.bin", vec![7u8; 16]);
+            let result = names
+                .iter()
+                .fold(builder, |builder, name| {
+                    builder.push_subtask_data(vec![0u8; 16]).subtask_name(*name)
+                })
+                .build();
+            match result {
+                Err(Error::InvalidSubtaskName(_)) => {}
+                other => panic!("unexpected result for {:?}: {:?}", names, other),
+            }
+        }
+    }
+
+    #[test]
+    fn subtask_failures() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let mut task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .push_subtask_data(vec![0u8; 16])
+            .build()
+            .unwrap();
+        let failure = SubtaskFailure {
+            subtask: Some("subtask_0".into()),
+            golem_subtask_id: "1234".into(),
+            status: "Failure".into(),
+            stderr: Some("RuntimeError: unreachable executed".into()),
+            ..SubtaskFailure::default()
+        };
+        task.set_subtask_failures(vec![failure.clone()]);
+
+        match ComputedTask::try_from(task.clone()) {
+            Err(Error::SubtaskFailed { subtask, failures }) => {
+                assert_eq!(subtask, "subtask_0");
+                assert_eq!(failures, vec![failure.clone()]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let output_dir = task.options().output_dir_path().join("subtask_0");
+        fs::write(output_dir.join("out"), b"output").unwrap();
+        let computed_task = ComputedTask::try_from(task).unwrap();
+        assert_eq!(computed_task.subtasks[0].failures, vec![failure]);
+    }
+
+    #[test]
+    fn partial_results() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .partial_results(true)
+            .push_subtask_data(vec![0u8; 16])
+            .push_subtask_data(vec![1u8; 16])
+            .build()
+            .unwrap();
+
+        let output_dir = task.options().output_dir_path().join("subtask_1");
+        fs::write(output_dir.join("out"), b"output").unwrap();
+        let computed_task = ComputedTask::try_from(task).unwrap();
+        assert_eq!(computed_task.subtasks.len(), 1);
+        assert_eq!(computed_task.subtasks[0].name, "subtask_1");
+        assert_eq!(computed_task.failed_subtasks.len(), 1);
+        assert_eq!(computed_task.failed_subtasks[0].name, "subtask_0");
+        match &computed_task.failed_subtasks[0].error {
+            Error::FileError(..) => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deduplicated_inputs() {
+        let workspace = tempfile::tempdir().unwrap();
+        let binary = GWasmBinary::new(Vec::new(), Vec::new());
+        let task = TaskBuilder::try_new(&workspace, binary)
+            .unwrap()
+            .deduplicate_inputs(true)
+            .parallelism(2)
+            .push_subtask_data(vec![0u8; 16])
+            .push_subtask_data(vec![1u8; 16])
+            .push_subtask_data(vec![0u8; 16])
+            .build()
+            .unwrap();
+
+        for (i, (name, subtask)) in task.options().subtasks().enumerate() {
+            let input = subtask.exec_args().next().unwrap();
+            let path = task.options().input_dir_path().join(name).join(input);
+            assert_eq!(fs::read(path).unwrap(), vec![(i % 2) as u8; 16]);
+        }
+    }
+}
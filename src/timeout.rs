@@ -1,87 +1,301 @@
 //! Types representing Golem Task's timeout values
 use super::{error::Error, Result};
-use chrono::naive::NaiveTime;
-use serde::{Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
 use std::fmt;
 use std::str::FromStr;
+use std::time::Duration;
 
-/// Wrapper type for [`NaiveTime`]
+/// Wrapper type for [`Duration`] with a granularity of whole seconds
 ///
-/// `Timeout` can be generated from `str` only, and accepts format `%H:%M:%S`. Note
-/// that zero timeout is treated as an error: [`Error::ZeroTimeoutError`].
+/// `Timeout` can be generated from a [`Duration`], or from `str` in either of
+/// the following formats:
+/// * `%H:%M:%S`, where the number of hours is unbounded, e.g. `36:00:00`
+/// * a sequence of numbers followed by units `d`, `h`, `m` or `s`, given in
+///   this order, e.g. `90s`, `15m`, `2h30m` or `1d`
 ///
-/// [`NaiveTime`]: https://docs.rs/chrono/0.4.7/chrono/naive/struct.NaiveTime.html
+/// It is always serialized in format `%H:%M:%S` expected by Golem, and can be
+/// deserialized from a string in any of the above formats, or from a number
+/// of seconds. Note that zero timeout is treated as an error:
+/// [`Error::ZeroTimeoutError`].
+///
+/// [`Duration`]: https://doc.rust-lang.org/std/time/struct.Duration.html
 /// [`Error::ZeroTimeoutError`]: ../error/enum.Error.html#variant.ZeroTimeoutError
 ///
 /// # Example:
 /// ```rust
 /// use gwasm_api::timeout::Timeout;
+/// use std::convert::TryFrom;
 /// use std::str::FromStr;
-/// use chrono::naive::NaiveTime;
+/// use std::time::Duration;
 ///
 /// assert!(Timeout::from_str("00:00:10").is_ok());
+/// assert_eq!(Timeout::from_str("2h30m").unwrap().to_string(), "02:30:00");
 /// assert!(Timeout::from_str("10").is_err());
 /// assert!(Timeout::from_str("00:00:00").is_err());
+///
+/// let timeout = Timeout::try_from(Duration::from_secs(36 * 3600)).unwrap();
+/// assert_eq!(timeout.to_string(), "36:00:00");
+/// assert_eq!(Duration::from(timeout), Duration::from_secs(36 * 3600));
 /// ```
-#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
-pub struct Timeout(#[serde(serialize_with = "serialize_naive_time")] NaiveTime);
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Timeout(Duration);
+
+impl Timeout {
+    /// Returns the total number of whole seconds in this `Timeout`
+    pub fn as_secs(&self) -> u64 {
+        self.0.as_secs()
+    }
+
+    /// Returns this `Timeout` as a [`Duration`]
+    ///
+    /// [`Duration`]: https://doc.rust-lang.org/std/time/struct.Duration.html
+    pub fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl TryFrom<Duration> for Timeout {
+    type Error = Error;
+
+    /// Converts `duration` into a `Timeout`, rounding it up to whole seconds
+    fn try_from(duration: Duration) -> Result<Self> {
+        let secs = duration
+            .as_secs()
+            .saturating_add(u64::from(duration.subsec_nanos() > 0));
+        if secs == 0 {
+            Err(Error::ZeroTimeoutError)
+        } else {
+            Ok(Self(Duration::from_secs(secs)))
+        }
+    }
+}
+
+impl From<Timeout> for Duration {
+    fn from(timeout: Timeout) -> Self {
+        timeout.0
+    }
+}
 
 impl FromStr for Timeout {
     type Err = Error;
 
     fn from_str(value: &str) -> Result<Self> {
-        let timeout = NaiveTime::parse_from_str(value, "%H:%M:%S")?;
-        if timeout == NaiveTime::from_hms(0, 0, 0) {
-            Err(Error::ZeroTimeoutError)
+        let secs = if value.contains(':') {
+            parse_clock(value)
         } else {
-            Ok(Self(timeout))
+            parse_units(value)
+        };
+        let secs = secs.map_err(|reason| Error::InvalidTimeout {
+            value: value.to_owned(),
+            reason,
+        })?;
+        Self::try_from(Duration::from_secs(secs))
+    }
+}
+
+type ParseResult<T> = std::result::Result<T, &'static str>;
+
+// parses `%H:%M:%S` into seconds
+fn parse_clock(value: &str) -> ParseResult<u64> {
+    const FORMAT: &str = "expected format HH:MM:SS";
+    let field = |field: &str, max: u64| {
+        if field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(FORMAT);
+        }
+        match field.parse::<u64>() {
+            Ok(value) if value <= max => Ok(value),
+            Ok(_) => Err("minutes and seconds must be less than 60"),
+            Err(_) => Err("value too large"),
+        }
+    };
+
+    let mut parts = value.split(':');
+    let (hours, minutes, seconds) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(hours), Some(minutes), Some(seconds)) if parts.next().is_none() => (
+            field(hours, u64::MAX)?,
+            field(minutes, 59)?,
+            field(seconds, 59)?,
+        ),
+        _ => return Err(FORMAT),
+    };
+    hours
+        .checked_mul(3600)
+        .and_then(|secs| secs.checked_add(minutes * 60 + seconds))
+        .ok_or("value too large")
+}
+
+// parses a sequence of numbers with units, such as `2h30m`, into seconds
+fn parse_units(value: &str) -> ParseResult<u64> {
+    const UNITS: &[(char, u64)] = &[('d', 86_400), ('h', 3600), ('m', 60), ('s', 1)];
+
+    if value.is_empty() {
+        return Err("empty value");
+    }
+    let mut rest = value;
+    let mut next_unit = 0;
+    let mut secs: u64 = 0;
+    while !rest.is_empty() {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return Err("expected a number, e.g. 90s, 15m, 2h30m or 1d");
+        }
+        let (number, tail) = rest.split_at(digits);
+        let unit = tail
+            .chars()
+            .next()
+            .ok_or("missing unit, expected one of d, h, m, s, or format HH:MM:SS")?;
+        let position = UNITS
+            .iter()
+            .position(|(name, _)| *name == unit)
+            .ok_or("unknown unit, expected one of d, h, m, s")?;
+        if position < next_unit {
+            return Err("units must be given in order d, h, m, s, each at most once");
         }
+        next_unit = position + 1;
+        secs = number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(UNITS[position].1))
+            .and_then(|number| number.checked_add(secs))
+            .ok_or("value too large")?;
+        rest = &tail[unit.len_utf8()..];
     }
+    Ok(secs)
 }
 
 impl fmt::Display for Timeout {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0.format("%H:%M:%S").to_string())
+        let secs = self.as_secs();
+        write!(
+            f,
+            "{:02}:{:02}:{:02}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )
+    }
+}
+
+impl Serialize for Timeout {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timeout {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        d.deserialize_any(TimeoutVisitor)
     }
 }
 
-fn serialize_naive_time<S: Serializer>(
-    timeout: &NaiveTime,
-    s: S,
-) -> std::result::Result<S::Ok, S::Error> {
-    s.serialize_str(&timeout.format("%H:%M:%S").to_string())
+struct TimeoutVisitor;
+
+impl<'de> de::Visitor<'de> for TimeoutVisitor {
+    type Value = Timeout;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a timeout such as \"01:30:00\" or \"90m\", or a number of seconds"
+        )
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Timeout, E> {
+        Timeout::from_str(value).map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<Timeout, E> {
+        Timeout::try_from(Duration::from_secs(value)).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<Timeout, E> {
+        match u64::try_from(value) {
+            Ok(value) => self.visit_u64(value),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Signed(value), &self)),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn secs(secs: u64) -> Timeout {
+        Timeout(Duration::from_secs(secs))
+    }
+
     #[test]
     fn valid_input() {
+        assert_eq!(Timeout::from_str("00:00:10").unwrap(), secs(10));
+        assert_eq!(Timeout::from_str("00:10:00").unwrap(), secs(600));
+        assert_eq!(Timeout::from_str("10:00:00").unwrap(), secs(36_000));
+        assert_eq!(Timeout::from_str("23:59:59").unwrap(), secs(86_399));
+        assert_eq!(Timeout::from_str("24:00:00").unwrap(), secs(86_400));
+        assert_eq!(Timeout::from_str("100:00:01").unwrap(), secs(360_001));
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(Timeout::from_str("10").is_err());
+        assert!(Timeout::from_str("10:00").is_err());
+        assert!(Timeout::from_str("").is_err());
+        assert!(Timeout::from_str("00:60:00").is_err());
+        assert!(Timeout::from_str("00:00:+1").is_err());
+        assert!(Timeout::from_str("00:00:00:10").is_err());
+        assert!(Timeout::from_str("00:00:00").is_err());
+        assert!(Timeout::from_str("0s").is_err());
+        assert!(Timeout::from_str("30m2h").is_err());
+        assert!(Timeout::from_str("1h1h").is_err());
+        assert!(Timeout::from_str("1w").is_err());
+        assert!(Timeout::from_str("h").is_err());
+        assert!(Timeout::from_str("1h 30m").is_err());
+    }
+
+    #[test]
+    fn human_friendly_input() {
+        assert_eq!(Timeout::from_str("90s").unwrap(), secs(90));
+        assert_eq!(Timeout::from_str("15m").unwrap(), secs(900));
+        assert_eq!(Timeout::from_str("2h30m").unwrap(), secs(9000));
+        assert_eq!(Timeout::from_str("1d").unwrap(), secs(86_400));
+        assert_eq!(Timeout::from_str("1d2h3m4s").unwrap(), secs(93_784));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = Timeout::from_str("30m2h").unwrap_err();
         assert_eq!(
-            Timeout::from_str("00:00:10").unwrap(),
-            Timeout(NaiveTime::from_hms(0, 0, 10))
-        );
-        assert_eq!(
-            Timeout::from_str("00:10:00").unwrap(),
-            Timeout(NaiveTime::from_hms(0, 10, 0))
+            err.to_string(),
+            "invalid timeout \"30m2h\": units must be given in order d, h, m, s, each at most once"
         );
+        let err = Timeout::from_str("00:61:00").unwrap_err();
         assert_eq!(
-            Timeout::from_str("10:00:00").unwrap(),
-            Timeout(NaiveTime::from_hms(10, 0, 0))
+            err.to_string(),
+            "invalid timeout \"00:61:00\": minutes and seconds must be less than 60"
         );
+    }
+
+    #[test]
+    fn duration_conversion() {
         assert_eq!(
-            Timeout::from_str("23:59:59").unwrap(),
-            Timeout(NaiveTime::from_hms(23, 59, 59))
+            Timeout::try_from(Duration::from_millis(1500)).unwrap(),
+            secs(2)
         );
+        assert!(Timeout::try_from(Duration::from_secs(0)).is_err());
+        assert_eq!(Duration::from(secs(90)), Duration::from_secs(90));
     }
 
     #[test]
-    fn invalid_input() {
-        assert!(Timeout::from_str("10").is_err());
-        assert!(Timeout::from_str("10:00").is_err());
-        assert!(Timeout::from_str("").is_err());
-        assert!(Timeout::from_str("24:00:00").is_err());
-        assert!(Timeout::from_str("00:00:00").is_err());
+    fn serialization() {
+        let json = serde_json::to_string(&secs(90_061)).unwrap();
+        assert_eq!(json, "\"25:01:01\"");
+        let timeout: Timeout = serde_json::from_str(&json).unwrap();
+        assert_eq!(timeout, secs(90_061));
+        let timeout: Timeout = serde_json::from_str("\"1d\"").unwrap();
+        assert_eq!(timeout, secs(86_400));
+        let timeout: Timeout = serde_json::from_str("90").unwrap();
+        assert_eq!(timeout, secs(90));
+        assert!(serde_json::from_str::<Timeout>("0").is_err());
+        assert!(serde_json::from_str::<Timeout>("-1").is_err());
     }
 }
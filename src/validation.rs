@@ -0,0 +1,114 @@
+//! Validation of gWasm binaries before submitting a task
+//!
+//! Since a malformed binary is only discovered by Golem after the task has
+//! been paid for, [`TaskBuilder`] can optionally validate the binary when
+//! building the task (see [`TaskBuilder::validate_binary`]). The validation
+//! checks that:
+//! * the Wasm module has a correct header, and all of its sections can be parsed,
+//! * the Wasm module exports one of the Emscripten [`ENTRY_POINTS`],
+//! * the JavaScript glue code references the Wasm file by the name it will be
+//!   given in the task's workspace, i.e., `{name}.wasm`.
+//!
+//! [`TaskBuilder`]: ../task/struct.TaskBuilder.html
+//! [`TaskBuilder::validate_binary`]: ../task/struct.TaskBuilder.html#method.validate_binary
+//! [`ENTRY_POINTS`]: constant.ENTRY_POINTS.html
+use super::{task::GWasmBinary, wasm::WasmModule, Result};
+
+/// Names of the entry point functions one of which is exported by Wasm
+/// modules generated by Emscripten
+pub const ENTRY_POINTS: &[&str] = &["main", "_main", "__main_argc_argv"];
+
+/// Enum describing why a gWasm binary failed validation
+#[derive(Debug, thiserror::Error)]
+pub enum ValidationError {
+    /// The Wasm module does not start with the `\0asm` magic number
+    #[error("not a Wasm module: bad magic number")]
+    BadMagic,
+
+    /// The Wasm module's version is not supported
+    #[error("unsupported Wasm version {0}")]
+    UnsupportedVersion(u32),
+
+    /// The Wasm module could not be parsed
+    #[error("malformed Wasm module at offset {offset}: {reason}")]
+    Malformed {
+        /// Offset in the module at which the error was encountered
+        offset: usize,
+        /// Description of the error
+        reason: &'static str,
+    },
+
+    /// The Wasm module does not export any of the Emscripten entry points
+    #[error(
+        "Wasm module does not export an entry point (one of {:?})",
+        ENTRY_POINTS
+    )]
+    MissingEntryPoint,
+
+    /// The JavaScript glue code does not reference the Wasm file
+    #[error("JavaScript file does not reference Wasm file \"{0}\"")]
+    WasmNameMismatch(String),
+}
+
+/// Validates `binary` for a task called `name`
+pub fn validate(binary: &GWasmBinary<'_>, name: &str) -> Result<()> {
+    let wasm = binary.wasm.read()?;
+    let module = WasmModule::parse(&wasm)?;
+    if !ENTRY_POINTS
+        .iter()
+        .any(|entry_point| module.exports_function(entry_point))
+    {
+        return Err(ValidationError::MissingEntryPoint.into());
+    }
+
+    let wasm_name = format!("{}.wasm", name);
+    let js = binary.js.read()?;
+    if !js
+        .windows(wasm_name.len())
+        .any(|window| window == wasm_name.as_bytes())
+    {
+        return Err(ValidationError::WasmNameMismatch(wasm_name).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::wasm::test::module;
+
+    const JS: &[u8] = b"var wasmBinaryFile = 'app.wasm';";
+
+    #[test]
+    fn valid_binary() {
+        let wasm = module(&[], &["main"]);
+        assert!(validate(&GWasmBinary::new(JS, wasm), "app").is_ok());
+    }
+
+    #[test]
+    fn missing_entry_point() {
+        let wasm = module(&[], &["memory"]);
+        match validate(&GWasmBinary::new(JS, wasm), "app") {
+            Err(Error::ValidationError(ValidationError::MissingEntryPoint)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn wasm_name_mismatch() {
+        let wasm = module(&[], &["_main"]);
+        match validate(&GWasmBinary::new(JS, wasm), "other") {
+            Err(Error::ValidationError(ValidationError::WasmNameMismatch(name))) => {
+                assert_eq!(name, "other.wasm")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn empty_binary() {
+        assert!(validate(&GWasmBinary::new(Vec::new(), Vec::new()), "app").is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! Minimal parser of Wasm modules
+//!
+//! Only the parts of a module needed to check it before submitting a gWasm
+//! task are decoded, i.e., the module's header, the layout of its sections,
+//! and its import and export sections.
+use super::{error::Error, validation::ValidationError, Result};
+use std::str;
+
+const MAGIC: &[u8] = b"\0asm";
+const VERSION: u32 = 1;
+
+const IMPORT_SECTION: u8 = 2;
+const EXPORT_SECTION: u8 = 7;
+const MAX_SECTION_ID: u8 = 13;
+
+/// Kind of an imported or exported entity
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExternalKind {
+    /// Function
+    Function,
+    /// Table
+    Table,
+    /// Memory
+    Memory,
+    /// Global variable
+    Global,
+    /// Exception tag
+    Tag,
+}
+
+/// Entity imported by a Wasm module
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    /// Name of the module the entity is imported from, e.g., `env`
+    pub module: String,
+    /// Name of the entity
+    pub name: String,
+    /// Kind of the entity
+    pub kind: ExternalKind,
+}
+
+/// Entity exported by a Wasm module
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    /// Name of the entity
+    pub name: String,
+    /// Kind of the entity
+    pub kind: ExternalKind,
+}
+
+/// Imports and exports of a parsed Wasm module
+#[derive(Debug, Clone, Default)]
+pub struct WasmModule {
+    imports: Vec<Import>,
+    exports: Vec<Export>,
+}
+
+impl WasmModule {
+    /// Parses a Wasm module from its binary representation
+    ///
+    /// The module's header and the layout of all of its sections are
+    /// validated, and its imports and exports are decoded.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(ValidationError::BadMagic.into());
+        }
+        let version = reader.u32_le()?;
+        if version != VERSION {
+            return Err(ValidationError::UnsupportedVersion(version).into());
+        }
+
+        let mut module = Self::default();
+        while !reader.is_empty() {
+            let offset = reader.offset;
+            let id = reader.byte()?;
+            if id > MAX_SECTION_ID {
+                return Err(reader.malformed_at(offset, "unknown section id"));
+            }
+            let size = reader.var_u32()? as usize;
+            let payload = reader.bytes(size)?;
+            let mut section = Reader {
+                bytes: payload,
+                offset: 0,
+                base: reader.base + reader.offset - size,
+            };
+            match id {
+                IMPORT_SECTION => module.imports = section.imports()?,
+                EXPORT_SECTION => module.exports = section.exports()?,
+                _ => {}
+            }
+        }
+
+        Ok(module)
+    }
+
+    /// Returns an [`Iterator`] over the module's imports
+    ///
+    /// [`Iterator`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html
+    pub fn imports(&self) -> impl Iterator<Item = &Import> {
+        self.imports.iter()
+    }
+
+    /// Returns an [`Iterator`] over the module's exports
+    ///
+    /// [`Iterator`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html
+    pub fn exports(&self) -> impl Iterator<Item = &Export> {
+        self.exports.iter()
+    }
+
+    /// Checks whether the module exports a function called `name`
+    pub fn exports_function(&self, name: &str) -> bool {
+        self.exports
+            .iter()
+            .any(|export| export.kind == ExternalKind::Function && export.name == name)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    // offset of `bytes` within the whole module, for error reporting
+    base: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            offset: 0,
+            base: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+
+    fn malformed_at(&self, offset: usize, reason: &'static str) -> Error {
+        ValidationError::Malformed {
+            offset: self.base + offset,
+            reason,
+        }
+        .into()
+    }
+
+    fn malformed(&self, reason: &'static str) -> Error {
+        self.malformed_at(self.offset, reason)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .bytes
+            .get(self.offset)
+            .ok_or_else(|| self.malformed("unexpected end of module"))?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| self.malformed("unexpected end of module"))?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn u32_le(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn var_u64(&mut self, max_bits: u32) -> Result<u64> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            result |= u64::from(byte & 0x7f) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+            if shift >= max_bits {
+                return Err(self.malformed("integer representation too long"));
+            }
+        }
+    }
+
+    fn var_u32(&mut self) -> Result<u32> {
+        let value = self.var_u64(35)?;
+        if value > u64::from(u32::MAX) {
+            return Err(self.malformed("integer too large"));
+        }
+        Ok(value as u32)
+    }
+
+    fn name(&mut self) -> Result<String> {
+        let len = self.var_u32()? as usize;
+        let bytes = self.bytes(len)?;
+        str::from_utf8(bytes)
+            .map(ToOwned::to_owned)
+            .map_err(|_| self.malformed("invalid UTF-8 name"))
+    }
+
+    fn limits(&mut self) -> Result<()> {
+        let flags = self.var_u32()?;
+        self.var_u64(70)?;
+        if flags & 0x1 != 0 {
+            self.var_u64(70)?;
+        }
+        Ok(())
+    }
+
+    fn external_kind(&mut self) -> Result<ExternalKind> {
+        match self.byte()? {
+            0x00 => Ok(ExternalKind::Function),
+            0x01 => Ok(ExternalKind::Table),
+            0x02 => Ok(ExternalKind::Memory),
+            0x03 => Ok(ExternalKind::Global),
+            0x04 => Ok(ExternalKind::Tag),
+            _ => Err(self.malformed("unknown external kind")),
+        }
+    }
+
+    fn imports(&mut self) -> Result<Vec<Import>> {
+        let count = self.var_u32()?;
+        let mut imports = Vec::new();
+        for _ in 0..count {
+            let module = self.name()?;
+            let name = self.name()?;
+            let kind = self.external_kind()?;
+            match kind {
+                ExternalKind::Function => {
+                    self.var_u32()?;
+                }
+                ExternalKind::Table => {
+                    self.byte()?;
+                    self.limits()?;
+                }
+                ExternalKind::Memory => self.limits()?,
+                ExternalKind::Global => {
+                    self.byte()?;
+                    self.byte()?;
+                }
+                ExternalKind::Tag => {
+                    self.byte()?;
+                    self.var_u32()?;
+                }
+            }
+            imports.push(Import { module, name, kind });
+        }
+        self.end_of_section()?;
+        Ok(imports)
+    }
+
+    fn exports(&mut self) -> Result<Vec<Export>> {
+        let count = self.var_u32()?;
+        let mut exports = Vec::new();
+        for _ in 0..count {
+            let name = self.name()?;
+            let kind = self.external_kind()?;
+            self.var_u32()?;
+            exports.push(Export { name, kind });
+        }
+        self.end_of_section()?;
+        Ok(exports)
+    }
+
+    fn end_of_section(&self) -> Result<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self.malformed("section size mismatch"))
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut bytes = vec![name.len() as u8];
+        bytes.extend_from_slice(name.as_bytes());
+        bytes
+    }
+
+    fn var_u32(mut value: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    fn section(id: u8, payload: Vec<u8>) -> Vec<u8> {
+        let mut bytes = vec![id];
+        bytes.extend(var_u32(payload.len()));
+        bytes.extend(payload);
+        bytes
+    }
+
+    /// Builds a module importing functions `imports` from `env`, and
+    /// exporting functions `exports`
+    pub(crate) fn module(imports: &[&str], exports: &[&str]) -> Vec<u8> {
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+
+        let mut payload = vec![imports.len() as u8];
+        for import in imports {
+            payload.extend(name("env"));
+            payload.extend(name(import));
+            payload.extend(&[0x00, 0x00]);
+        }
+        bytes.extend(section(IMPORT_SECTION, payload));
+
+        let mut payload = vec![exports.len() as u8];
+        for (i, export) in exports.iter().enumerate() {
+            payload.extend(name(export));
+            payload.extend(&[0x00, i as u8]);
+        }
+        bytes.extend(section(EXPORT_SECTION, payload));
+
+        bytes
+    }
+
+    #[test]
+    fn valid_module() {
+        let module = WasmModule::parse(&module(&["_fd_write"], &["main", "memory"])).unwrap();
+        assert_eq!(
+            module.imports().collect::<Vec<_>>(),
+            vec![&Import {
+                module: "env".into(),
+                name: "_fd_write".into(),
+                kind: ExternalKind::Function,
+            }]
+        );
+        assert!(module.exports_function("main"));
+        assert!(!module.exports_function("_main"));
+    }
+
+    #[test]
+    fn invalid_header() {
+        match WasmModule::parse(b"\0wasm\x01\0\0") {
+            Err(Error::ValidationError(ValidationError::BadMagic)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match WasmModule::parse(b"\0asm\x02\0\0\0") {
+            Err(Error::ValidationError(ValidationError::UnsupportedVersion(2))) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn truncated_module() {
+        let bytes = module(&["_fd_write"], &["main"]);
+        for len in &[9, 12, bytes.len() - 1] {
+            match WasmModule::parse(&bytes[..*len]) {
+                Err(Error::ValidationError(ValidationError::Malformed { .. })) => {}
+                other => panic!("unexpected result for length {}: {:?}", len, other),
+            }
+        }
+    }
+}
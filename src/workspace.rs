@@ -0,0 +1,474 @@
+//! Lifecycle management of task workspaces
+//!
+//! A workspace is the dir in which [`TaskBuilder::build`] creates the task's
+//! `in` and `out` dirs. What happens if those dirs already exist, and whether
+//! the workspace is cleaned up afterwards, is governed by [`WorkspacePolicy`].
+//!
+//! [`TaskBuilder::build`]: ../task/struct.TaskBuilder.html#method.build
+//! [`WorkspacePolicy`]: enum.WorkspacePolicy.html
+use super::{
+    digest::Digest,
+    error::{Error, FileContext},
+    ProgressUpdate, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, PoisonError,
+    },
+    thread,
+};
+use tempfile::TempDir;
+
+/// Specifies how [`TaskBuilder::build`] treats the workspace
+///
+/// [`TaskBuilder::build`]: ../task/struct.TaskBuilder.html#method.build
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum WorkspacePolicy {
+    /// Fails with [`Error::FileError`] if the task's dirs or files already
+    /// exist in the workspace
+    ///
+    /// The check is advisory: it's done before the task is built, and doesn't
+    /// guard against another process creating the paths in the meantime.
+    ///
+    /// [`Error::FileError`]: ../error/enum.Error.html#variant.FileError
+    #[default]
+    FailIfExists,
+    /// Replaces the task's dirs if they already exist in the workspace
+    ///
+    /// The existing dirs are only removed once the new ones have been
+    /// successfully created.
+    Overwrite,
+    /// Creates a fresh, uniquely named dir inside the workspace and uses it
+    /// as the task's workspace
+    ///
+    /// The dir is owned by the built [`Task`], and removed when the `Task` and
+    /// the [`ComputedTask`] created from it are dropped, unless
+    /// [`Task::keep_workspace`] is called.
+    ///
+    /// [`Task`]: ../task/struct.Task.html
+    /// [`ComputedTask`]: ../task/struct.ComputedTask.html
+    /// [`Task::keep_workspace`]: ../task/struct.Task.html#method.keep_workspace
+    Temp,
+}
+
+/// Temporary workspace removed on drop, unless explicitly kept
+#[derive(Debug)]
+pub struct TempWorkspace {
+    path: PathBuf,
+    dir: Mutex<Option<TempDir>>,
+}
+
+impl TempWorkspace {
+    fn new(parent: &Path) -> Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix("gwasm")
+            .tempdir_in(parent)
+            .file_context(parent)?;
+        Ok(Self {
+            path: dir.path().to_owned(),
+            dir: Mutex::new(Some(dir)),
+        })
+    }
+
+    /// Path to the workspace
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Prevents the workspace from being removed on drop
+    pub fn keep(&self) {
+        let mut dir = self.dir.lock().unwrap_or_else(PoisonError::into_inner);
+        // forgetting the `TempDir` disarms its cleanup
+        if let Some(dir) = dir.take() {
+            mem::forget(dir);
+        }
+    }
+}
+
+/// Prepares `workspace` for creating the task's dirs, `in` and `out`,
+/// according to `policy`
+///
+/// Returns the path to the actual workspace, and, in case of
+/// [`WorkspacePolicy::Temp`], the guard owning it.
+pub(crate) fn prepare(
+    workspace: &Path,
+    policy: WorkspacePolicy,
+) -> Result<(PathBuf, Option<Arc<TempWorkspace>>)> {
+    match policy {
+        WorkspacePolicy::FailIfExists => {
+            let paths = [
+                input_dir(workspace),
+                output_dir(workspace),
+                sidecar(workspace),
+                manifest(workspace),
+            ];
+            for path in &paths {
+                if fs::symlink_metadata(path).is_ok() {
+                    return Err(io::Error::from(io::ErrorKind::AlreadyExists)).file_context(path);
+                }
+            }
+            Ok((workspace.to_owned(), None))
+        }
+        WorkspacePolicy::Overwrite => Ok((workspace.to_owned(), None)),
+        WorkspacePolicy::Temp => {
+            let temp = TempWorkspace::new(workspace)?;
+            Ok((temp.path().to_owned(), Some(Arc::new(temp))))
+        }
+    }
+}
+
+/// Staging dir in which the task's dirs are created before being moved
+/// into the workspace
+///
+/// Unless committed, the staging dir and all of its contents are removed
+/// on drop.
+pub(crate) struct Staging {
+    dir: TempDir,
+    workspace: PathBuf,
+}
+
+impl Staging {
+    pub(crate) fn new(workspace: &Path) -> Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix(".gwasm-staging")
+            .tempdir_in(workspace)
+            .file_context(workspace)?;
+        Ok(Self {
+            dir,
+            workspace: workspace.to_owned(),
+        })
+    }
+
+    pub(crate) fn input_dir(&self) -> PathBuf {
+        input_dir(self.dir.path())
+    }
+
+    pub(crate) fn output_dir(&self) -> PathBuf {
+        output_dir(self.dir.path())
+    }
+
+    pub(crate) fn sidecar(&self) -> PathBuf {
+        sidecar(self.dir.path())
+    }
+
+    pub(crate) fn manifest(&self) -> PathBuf {
+        manifest(self.dir.path())
+    }
+
+    /// Moves the task's dirs, sidecar and manifest from the staging dir into
+    /// the workspace
+    ///
+    /// With [`WorkspacePolicy::Overwrite`], the existing ones are first moved
+    /// aside into the staging dir, and only removed together with it once all
+    /// the new ones are in place. If any move fails, all the moves done so far
+    /// are reverted, leaving the workspace as it was.
+    pub(crate) fn commit(self, policy: WorkspacePolicy) -> Result<()> {
+        let paths = [
+            (self.input_dir(), input_dir(&self.workspace)),
+            (self.output_dir(), output_dir(&self.workspace)),
+            (self.sidecar(), sidecar(&self.workspace)),
+            (self.manifest(), manifest(&self.workspace)),
+        ];
+
+        let mut moves = Vec::new();
+        let result = self.swap(&paths, policy, &mut moves);
+        if result.is_err() {
+            for (from, to) in moves.into_iter().rev() {
+                let _ = fs::rename(to, from);
+            }
+        }
+        result
+    }
+
+    /// Moves the staged paths into the workspace, recording each completed
+    /// move in `moves`
+    fn swap(
+        &self,
+        paths: &[(PathBuf, PathBuf)],
+        policy: WorkspacePolicy,
+        moves: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<()> {
+        if policy == WorkspacePolicy::Overwrite {
+            let old = self.dir.path().join("old");
+            fs::create_dir(&old).file_context(&old)?;
+            for (_, committed) in paths {
+                if fs::symlink_metadata(committed).is_ok() {
+                    let name = committed.file_name().expect("workspace entry has a name");
+                    move_path(committed.clone(), old.join(name), moves)?;
+                }
+            }
+        }
+        for (staged, committed) in paths {
+            move_path(staged.clone(), committed.clone(), moves)?;
+        }
+        Ok(())
+    }
+}
+
+fn move_path(from: PathBuf, to: PathBuf, moves: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    fs::rename(&from, &to).file_context(&from)?;
+    moves.push((from, to));
+    Ok(())
+}
+
+/// Information about the task kept in the workspace next to the task's dirs,
+/// which is not part of the manifest sent to Golem
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Sidecar {
+    /// Digests of all files in the task's input dir, keyed by their path
+    /// relative to it
+    pub(crate) inputs: BTreeMap<String, Digest>,
+    /// Whether the input files are verified when the task is accepted
+    #[serde(default)]
+    pub(crate) verify_inputs: bool,
+    /// Names of the replicas of each subtask
+    #[serde(default)]
+    pub(crate) replicas: BTreeMap<String, Vec<String>>,
+    /// Whether the output agreed on by the majority of replicas is accepted
+    #[serde(default)]
+    pub(crate) majority: bool,
+    /// Whether the task collects partial results
+    #[serde(default)]
+    pub(crate) partial_results: bool,
+    /// Hard spending cap of the task
+    #[serde(default)]
+    pub(crate) budget_cap: Option<f64>,
+    /// Task's metadata
+    #[serde(default)]
+    pub(crate) metadata: BTreeMap<String, String>,
+    /// Tags of each subtask, keyed by the subtask's name
+    #[serde(default)]
+    pub(crate) tags: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Sidecar {
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        let file = File::open(path).file_context(path)?;
+        serde_json::from_reader(io::BufReader::new(file)).map_err(|e| Error::CodecError(e.into()))
+    }
+
+    pub(crate) fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).file_context(path)?;
+        let mut writer = io::BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self).map_err(|e| Error::CodecError(e.into()))?;
+        writer.flush().file_context(path)
+    }
+}
+
+/// Total size of the files within `dir` and its subdirs, in bytes
+pub(crate) fn dir_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    let mut pending = vec![dir.to_owned()];
+    while let Some(path) = pending.pop() {
+        for entry in fs::read_dir(&path).file_context(&path)? {
+            let entry = entry.file_context(&path)?;
+            let metadata = entry.metadata().file_context(entry.path())?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+        }
+    }
+    Ok(size)
+}
+
+/// Path to the task's input dir within `workspace`
+pub(crate) fn input_dir(workspace: &Path) -> PathBuf {
+    workspace.join("in")
+}
+
+/// Path to the task's output dir within `workspace`
+pub(crate) fn output_dir(workspace: &Path) -> PathBuf {
+    workspace.join("out")
+}
+
+/// Path to the task's sidecar within `workspace`
+pub(crate) fn sidecar(workspace: &Path) -> PathBuf {
+    workspace.join("gwasm.json")
+}
+
+/// Path to the task's JSON manifest within `workspace`
+pub(crate) fn manifest(workspace: &Path) -> PathBuf {
+    workspace.join("task.json")
+}
+
+/// Runs `job` for every item of `jobs` on at most `parallelism` threads,
+/// reporting the fraction of completed jobs to `progress`
+///
+/// The results are returned in the order of `jobs`. Once any job fails,
+/// no new jobs are started, and the first error is returned.
+pub(crate) fn run_jobs<J, T, F>(
+    jobs: Vec<J>,
+    parallelism: usize,
+    progress: &dyn ProgressUpdate,
+    job: F,
+) -> Result<Vec<T>>
+where
+    J: Send,
+    T: Send,
+    F: Fn(J) -> Result<T> + Sync,
+{
+    let total = jobs.len();
+    let queue = Mutex::new(jobs.into_iter().enumerate());
+    let failed = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+
+    progress.start();
+    let results: Result<Vec<_>> = thread::scope(|scope| {
+        for _ in 0..parallelism.max(1).min(total) {
+            let (tx, queue, failed, job) = (tx.clone(), &queue, &failed, &job);
+            scope.spawn(move || {
+                while !failed.load(Ordering::SeqCst) {
+                    let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                    let (i, item) = match next {
+                        Some(next) => next,
+                        None => break,
+                    };
+                    let result = job(item);
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    if tx.send((i, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut results = Vec::with_capacity(total);
+        for (i, result) in rx {
+            results.push((i, result?));
+            progress.update(results.len() as f64 / total as f64);
+        }
+        Ok(results)
+    });
+    progress.stop();
+
+    let mut results = results?;
+    results.sort_by_key(|(i, _)| *i);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn fail_if_exists() {
+        let workspace = tempdir().unwrap();
+        assert!(prepare(workspace.path(), WorkspacePolicy::FailIfExists).is_ok());
+        fs::create_dir(output_dir(workspace.path())).unwrap();
+        assert!(prepare(workspace.path(), WorkspacePolicy::FailIfExists).is_err());
+
+        for path in &[sidecar, manifest] {
+            let workspace = tempdir().unwrap();
+            fs::write(path(workspace.path()), b"{}").unwrap();
+            assert!(prepare(workspace.path(), WorkspacePolicy::FailIfExists).is_err());
+        }
+    }
+
+    #[test]
+    fn overwrite() {
+        let workspace = tempdir().unwrap();
+        fs::create_dir(input_dir(workspace.path())).unwrap();
+        fs::write(input_dir(workspace.path()).join("in0"), b"stale").unwrap();
+        let (path, temp) = prepare(workspace.path(), WorkspacePolicy::Overwrite).unwrap();
+        assert_eq!(path, workspace.path());
+        assert!(temp.is_none());
+
+        let staging = Staging::new(&path).unwrap();
+        fs::create_dir(staging.input_dir()).unwrap();
+        fs::create_dir(staging.output_dir()).unwrap();
+        Sidecar::default().write(&staging.sidecar()).unwrap();
+        fs::write(staging.manifest(), b"{}").unwrap();
+        staging.commit(WorkspacePolicy::Overwrite).unwrap();
+        assert!(!input_dir(workspace.path()).join("in0").exists());
+        assert!(output_dir(workspace.path()).is_dir());
+        assert!(sidecar(workspace.path()).is_file());
+        assert!(manifest(workspace.path()).is_file());
+        assert_eq!(fs::read_dir(workspace.path()).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn overwrite_rollback() {
+        let workspace = tempdir().unwrap();
+        fs::create_dir(input_dir(workspace.path())).unwrap();
+        fs::write(input_dir(workspace.path()).join("in0"), b"old").unwrap();
+        fs::create_dir(output_dir(workspace.path())).unwrap();
+        fs::write(sidecar(workspace.path()), b"old").unwrap();
+        fs::write(manifest(workspace.path()), b"old").unwrap();
+
+        let staging = Staging::new(workspace.path()).unwrap();
+        fs::create_dir(staging.input_dir()).unwrap();
+        fs::create_dir(staging.output_dir()).unwrap();
+        Sidecar::default().write(&staging.sidecar()).unwrap();
+        // manifest missing, so moving it fails
+        assert!(staging.commit(WorkspacePolicy::Overwrite).is_err());
+        assert_eq!(
+            fs::read(input_dir(workspace.path()).join("in0")).unwrap(),
+            b"old"
+        );
+        assert_eq!(fs::read(sidecar(workspace.path())).unwrap(), b"old");
+        assert_eq!(fs::read(manifest(workspace.path())).unwrap(), b"old");
+        assert_eq!(fs::read_dir(workspace.path()).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn staging_rollback() {
+        let workspace = tempdir().unwrap();
+        let staging = Staging::new(workspace.path()).unwrap();
+        fs::create_dir(staging.input_dir()).unwrap();
+        fs::create_dir(staging.output_dir()).unwrap();
+        // sidecar missing, so moving it fails
+        assert!(staging.commit(WorkspacePolicy::FailIfExists).is_err());
+        assert_eq!(fs::read_dir(workspace.path()).unwrap().count(), 0);
+    }
+
+    struct NoProgress;
+
+    impl ProgressUpdate for NoProgress {
+        fn update(&self, _progress: f64) {}
+    }
+
+    #[test]
+    fn parallel_jobs() {
+        let jobs: Vec<u32> = (0..100).collect();
+        let results = run_jobs(jobs, 8, &NoProgress, |i| Ok(i * 2)).unwrap();
+        assert_eq!(results, (0..100).map(|i| i * 2).collect::<Vec<_>>());
+
+        let jobs: Vec<u32> = (0..100).collect();
+        let result = run_jobs(jobs, 8, &NoProgress, |i| {
+            if i == 42 {
+                Err(io::Error::from(io::ErrorKind::Other).into())
+            } else {
+                Ok(i)
+            }
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn temp() {
+        let workspace = tempdir().unwrap();
+        let (path, temp) = prepare(workspace.path(), WorkspacePolicy::Temp).unwrap();
+        assert!(path.starts_with(workspace.path()));
+        assert!(path.is_dir());
+        drop(temp);
+        assert!(!path.exists());
+
+        let (path, temp) = prepare(workspace.path(), WorkspacePolicy::Temp).unwrap();
+        temp.as_ref().unwrap().keep();
+        drop(temp);
+        assert!(path.is_dir());
+    }
+}